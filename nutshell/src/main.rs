@@ -3,6 +3,9 @@ use cpal::traits::HostTrait;
 use cpal::traits::StreamTrait;
 use cpal::Sample;
 use cpal::SampleRate;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::Sender;
@@ -17,8 +20,15 @@ use uninutsh::audio::music::NoteWriter;
 use uninutsh::audio::music::Song;
 use uninutsh::audio::Wave;
 use uninutsh::{
-    image::Color,
-    window::{EventHandler, Window, WindowEvent, WindowOptions},
+    image::{
+        palette::{Gradient, Palette},
+        recorder::Recorder,
+        BlendMode, Color, ColorSpace, Graphics,
+    },
+    window::{
+        input::Key, EventHandler, Filter, Fullscreen, Redraw, Scaling, Window, WindowEvent,
+        WindowOptions,
+    },
     Vector2,
 };
 #[derive(Copy, Clone)]
@@ -45,6 +55,9 @@ const LAYERS: usize = 4;
 const SAMPLE_RATE: u32 = 48000;
 const DEFINITION: u64 = 12;
 const CHANGE: u64 = 1;
+const RECORD_SCALE: u32 = 8;
+// how strongly the layers under the last one glow through it
+const LAYER_GLOW: u8 = 96;
 impl Nutshell {
     fn new(size: Vector2<u32>, layers: usize, definition: u64) -> Nutshell {
         let mut cells = Vec::with_capacity(layers);
@@ -344,10 +357,35 @@ struct AudioFrame {
 
 #[derive(Clone)]
 struct VideoSample {
-    pixels: Vec<Color>,
+    // one image per layer, the last one is drawn and the ones before are added on top
+    layers: Vec<Vec<Color>>,
     //pixels: [[Color; VIDEO_SAMPLE_WIDTH]; VIDEO_SAMPLE_HEIGHT],
 }
 
+impl VideoSample {
+    fn write(&self, graphics: &mut Graphics) {
+        let (last, below) = self.layers.split_last().unwrap();
+        let mut pixels = graphics.pixels_mut();
+        for y in 0..VIDEO_SAMPLE_HEIGHT {
+            for x in 0..VIDEO_SAMPLE_WIDTH {
+                pixels.set(x as u32, y as u32, last[y * VIDEO_SAMPLE_WIDTH + x]);
+            }
+        }
+        let mode = graphics.blend_mode();
+        graphics.set_blend_mode(BlendMode::Additive);
+        for layer in below {
+            for y in 0..VIDEO_SAMPLE_HEIGHT {
+                for x in 0..VIDEO_SAMPLE_WIDTH {
+                    let color = layer[y * VIDEO_SAMPLE_WIDTH + x];
+                    graphics.set_color(color.with_alpha(LAYER_GLOW));
+                    graphics.put(x as u32, y as u32);
+                }
+            }
+        }
+        graphics.set_blend_mode(mode);
+    }
+}
+
 struct VideoFrame {
     samples: Vec<VideoSample>,
     //samples: [VideoSample; VIDEO_SAMPLES_LENGHT],
@@ -363,24 +401,62 @@ struct ProcessingThread {
     pointer: Vector2<u32>,
     process_receiver: Receiver<Message>,
     process_sender: Sender<Message>,
+    gradient: Option<Gradient>,
 }
 
 impl ProcessingThread {
+    fn new(
+        process_receiver: Receiver<Message>,
+        process_sender: Sender<Message>,
+        gradient: Option<Gradient>,
+    ) -> ProcessingThread {
+        ProcessingThread {
+            nutshell: Nutshell::new(
+                Vector2::new(VIDEO_SAMPLE_WIDTH as u32, VIDEO_SAMPLE_HEIGHT as u32),
+                LAYERS,
+                DEFINITION,
+            ),
+            primary: None,
+            process_receiver,
+            process_sender,
+            pointer: Vector2::new(0, 0),
+            gradient,
+        }
+    }
     fn video_sample(&mut self) -> VideoSample {
-        let mut pixels = Vec::with_capacity(VIDEO_SAMPLE_HEIGHT * VIDEO_SAMPLE_HEIGHT);
-        for y in 0..VIDEO_SAMPLE_HEIGHT {
-            for x in 0..VIDEO_SAMPLE_WIDTH {
-                let index = self.nutshell.index_at(x as u32, y as u32);
-                let cell = self.nutshell.cells[self.nutshell.layers - 1][index];
-                let hue = cell.color as f64 / self.nutshell.definition as f64;
-                let saturation = cell.saturation as f64 / (self.nutshell.definition - 1) as f64;
-                let brightness = cell.brightness as f64 / (self.nutshell.definition - 1) as f64;
-                let color =
-                    Color::from_hsb([hue * 360.0 + 180.0, saturation, 1.0 - brightness], 255);
-                pixels.push(color);
+        // layer 0 is a copy of layer 1
+        let mut layers = Vec::with_capacity(self.nutshell.layers - 1);
+        for layer in 1..self.nutshell.layers {
+            let mut pixels = Vec::with_capacity(VIDEO_SAMPLE_WIDTH * VIDEO_SAMPLE_HEIGHT);
+            for y in 0..VIDEO_SAMPLE_HEIGHT {
+                for x in 0..VIDEO_SAMPLE_WIDTH {
+                    let index = self.nutshell.index_at(x as u32, y as u32);
+                    pixels.push(self.cell_color(self.nutshell.cells[layer][index]));
+                }
             }
+            layers.push(pixels);
+        }
+        VideoSample { layers }
+    }
+    fn cell_color(&self, cell: Cell) -> Color {
+        let hue = cell.color as f64 / self.nutshell.definition as f64;
+        let saturation = cell.saturation as f64 / (self.nutshell.definition - 1) as f64;
+        let brightness = cell.brightness as f64 / (self.nutshell.definition - 1) as f64;
+        match self.gradient.as_ref().and_then(|gradient| gradient.at(hue)) {
+            // the palette replaces the hue, the cell still fades and greys it
+            Some(color) => {
+                let [hue, palette_saturation, palette_brightness] = color.hsb();
+                Color::from_hsb(
+                    [
+                        hue,
+                        palette_saturation * saturation,
+                        palette_brightness * (1.0 - brightness),
+                    ],
+                    255,
+                )
+            }
+            None => Color::from_hsb([hue * 360.0 + 180.0, saturation, 1.0 - brightness], 255),
         }
-        VideoSample { pixels }
     }
     fn color(&mut self) -> u64 {
         let index = self.nutshell.index_at(self.pointer.x, self.pointer.y);
@@ -576,18 +652,27 @@ impl EventHandler for WindowThread {
     fn handle_event(&mut self, event: WindowEvent, window: &mut Window) {
         match event {
             WindowEvent::Exit => {
+                match window.stop_recording() {
+                    Some(Ok(frames)) => println!("{} frames recorded", frames),
+                    Some(Err(error)) => eprintln!("{}", error),
+                    None => {}
+                }
                 window.close();
             }
-            WindowEvent::Draw => match &self.drawing_frame {
+            WindowEvent::KeyPressed(Key::F11) => {
+                let fullscreen = match window.fullscreen() {
+                    Fullscreen::Off => Fullscreen::Borderless,
+                    _ => Fullscreen::Off,
+                };
+                window.set_fullscreen(fullscreen);
+            }
+            WindowEvent::KeyPressed(Key::F3) => {
+                window.show_stats(!window.is_showing_stats());
+            }
+            WindowEvent::Draw(_) => match &self.drawing_frame {
                 Some(sample) => {
                     let mut graphics = window.graphics().unwrap();
-                    for y in 0..VIDEO_SAMPLE_HEIGHT {
-                        for x in 0..VIDEO_SAMPLE_WIDTH {
-                            let color = sample.pixels[y * VIDEO_SAMPLE_WIDTH + x];
-                            graphics.set_color(color);
-                            graphics.put(x as u32, y as u32);
-                        }
-                    }
+                    sample.write(&mut graphics);
                     graphics.apply();
                     window.return_graphics(Some(graphics));
                 }
@@ -611,6 +696,7 @@ impl EventHandler for WindowThread {
                     self.try_draw(window);
                 }
             }
+            _ => {}
         }
     }
 }
@@ -628,42 +714,172 @@ struct AudioThread {
     frame: Option<Frame>,
     need_frame: bool,
     sample_index: usize,
-    reverb_length: usize,
-    reverb: Vec<Option<Vec<f32>>>,
-    back_reverb: Vec<Option<Vec<f32>>>,
-    reverb_echoes: usize,
-    reverb_indexes: Vec<usize>,
+    reverb: Reverb,
+}
+
+// The echoes mixed into the samples, shared by the live playback and `render`.
+struct Reverb {
+    length: usize,
+    echoes: usize,
+    buffers: Vec<Option<Vec<f32>>>,
+    back_buffers: Vec<Option<Vec<f32>>>,
+    indexes: Vec<usize>,
+    echo_amps: Vec<f32>,
+    wet: f32,
+}
+
+impl Reverb {
+    fn new() -> Reverb {
+        let length = SAMPLE_RATE as usize * 4;
+        let echoes = 6;
+        let mut buffers = Vec::with_capacity(echoes);
+        let mut back_buffers = Vec::with_capacity(echoes);
+        let mut rev_len = length;
+        for _i in 0..echoes {
+            buffers.push(Some(vec![0.0; rev_len]));
+            back_buffers.push(Some(vec![0.0; rev_len]));
+            rev_len /= 2;
+        }
+        let mut echo_amps = Vec::with_capacity(echoes);
+        let first_echo = 1.0 / 4.0;
+        let mut echo_amp = first_echo;
+        for _i in 0..echoes {
+            echo_amps.push(echo_amp);
+            echo_amp *= 1.0 / 2.0;
+        }
+        Reverb {
+            length,
+            echoes,
+            buffers,
+            back_buffers,
+            indexes: vec![0; echoes],
+            echo_amps,
+            wet: 1.0 / 2.0,
+        }
+    }
+    // One interleaved sample in, the sample to play out.
+    fn process(&mut self, audio_sample: f32) -> f32 {
+        let mut wet_sample = 0.0;
+        for echo_index in 0..self.echoes {
+            wet_sample += self.buffers[echo_index].as_ref().unwrap()[self.indexes[echo_index]];
+        }
+        wet_sample *= self.wet;
+        let final_sample = wet_sample + audio_sample * (1.0 - wet_sample);
+        for echo_index in 0..self.echoes {
+            let echo_amp = self.echo_amps[echo_index];
+            self.back_buffers[echo_index].as_mut().unwrap()[self.indexes[echo_index]] =
+                final_sample * echo_amp;
+        }
+        let mut rev_len = self.length;
+        for echo_index in 0..self.echoes {
+            self.indexes[echo_index] += 1;
+            if self.indexes[echo_index] >= rev_len {
+                self.indexes[echo_index] = 0;
+                let swap = self.buffers[echo_index].take();
+                self.buffers[echo_index] = self.back_buffers[echo_index].take();
+                self.back_buffers[echo_index] = swap;
+            }
+            rev_len /= 2;
+        }
+        final_sample
+    }
+}
+
+// Renders `frames` frames without a sound card or a display: numbered png files for the
+// video samples, audio.wav for the audio and timing.txt to mux them.
+fn render(directory: &Path, frames: usize, gradient: Option<Gradient>) {
+    fs::create_dir_all(directory).expect("can not create the render directory");
+    let (process_sender, process_receiver) = mpsc::channel();
+    let mut processing_thread = ProcessingThread::new(process_receiver, process_sender, gradient);
+    let mut graphics = Graphics::new(VIDEO_SAMPLE_WIDTH as u32, VIDEO_SAMPLE_HEIGHT as u32);
+    let mut wave = Wave {
+        samples: Vec::with_capacity(AUDIO_SAMPLES_LENGHT * frames),
+    };
+    // the same echoes as the live playback
+    let mut reverb = Reverb::new();
+    let mut image_index = 0;
+    for frame_index in 0..frames {
+        println!("rendering frame {} of {}", frame_index + 1, frames);
+        let mut frame = processing_thread.process();
+        for sample in frame.video.take().unwrap().samples {
+            sample.write(&mut graphics);
+            let path = directory.join(format!("{:06}.png", image_index));
+            if let Err(error) = graphics.sprite().save_scaled(path, RECORD_SCALE) {
+                panic!("{}", error);
+            }
+            image_index += 1;
+        }
+        let audio = frame.audio.take().unwrap();
+        for pair in audio.samples.chunks(2) {
+            let left = reverb.process(pair[0]);
+            let right = reverb.process(pair[1]);
+            wave.samples.push([left as f64, right as f64]);
+        }
+    }
+    wave.save(&directory.join("audio.wav"), SAMPLE_RATE);
+    let seconds = frames * SECONDS_PER_FRAME;
+    let timing = format!(
+        "images={}\n\
+         images_per_second={}\n\
+         image_seconds={}\n\
+         sample_rate={}\n\
+         channels=2\n\
+         audio_samples={}\n\
+         seconds={}\n\
+         # ffmpeg -framerate {} -i %06d.png -i audio.wav -c:v libx264 -pix_fmt yuv420p -c:a aac nutshell.mp4\n",
+        image_index,
+        VIDEO_SAMPLES_PER_SECOND,
+        1.0 / VIDEO_SAMPLES_PER_SECOND as f64,
+        SAMPLE_RATE,
+        wave.samples.len(),
+        seconds,
+        VIDEO_SAMPLES_PER_SECOND,
+    );
+    fs::write(directory.join("timing.txt"), timing).expect("can not write timing.txt");
+    println!(
+        "{} seconds rendered into {:?}",
+        seconds,
+        directory.as_os_str()
+    );
+}
+
+// the values following `name` up to the next flag
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a [String]> {
+    let start = args.iter().position(|arg| arg == name)? + 1;
+    let end = args[start..]
+        .iter()
+        .position(|arg| arg.starts_with("--"))
+        .map_or(args.len(), |length| start + length);
+    Some(&args[start..end])
 }
 
 fn main() {
     println!("Hello, world!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    // --palette colors.gpl maps the cell colors through the palette instead of the hue circle
+    let gradient = flag(&args, "--palette").map(|values| {
+        let path = PathBuf::from(values.first().expect("--palette needs a path"));
+        let palette = Palette::load(path.clone());
+        assert!(
+            !palette.is_empty(),
+            "--palette {} has no colors",
+            path.display()
+        );
+        Gradient::from_palette(&palette, ColorSpace::Lab)
+    });
+    if let Some(values) = flag(&args, "--render") {
+        let directory = PathBuf::from(values.first().expect("--render needs a directory"));
+        let frames = match values.get(1) {
+            Some(frames) => frames.parse().expect("--render frames must be a number"),
+            None => 1,
+        };
+        render(&directory, frames, gradient);
+        return;
+    }
     let (process_sender, audio_receiver) = mpsc::channel();
 
     let (audio_sender, process_receiver) = mpsc::channel();
     let (window_sender, window_receiver) = mpsc::channel();
-    let reverb_length = SAMPLE_RATE as usize * 4;
-    let reverb_echoes = 6;
-    let mut reverb = Vec::with_capacity(reverb_echoes);
-    let mut back_reverb = Vec::with_capacity(reverb_echoes);
-    let mut rev_len = reverb_length;
-
-    for _i in 0..reverb_echoes {
-        //reverb.as_mut().unwrap().push(0.0);
-        //back_reverb.as_mut().unwrap().push(0.0);
-        let mut revs = Vec::with_capacity(rev_len);
-        let mut back_revs = Vec::with_capacity(rev_len);
-        for _j in 0..rev_len {
-            revs.push(0.0);
-            back_revs.push(0.0);
-        }
-        rev_len /= 2;
-        reverb.push(Some(revs));
-        back_reverb.push(Some(back_revs));
-    }
-    let mut reverb_indexes = Vec::with_capacity(reverb_echoes);
-    for _i in 0..reverb_echoes {
-        reverb_indexes.push(0);
-    }
     let mut audio_thread = AudioThread {
         need_frame: true,
         sample_index: 0,
@@ -671,11 +887,7 @@ fn main() {
         audio_sender,
         audio_receiver,
         window_sender,
-        reverb,
-        back_reverb,
-        reverb_length,
-        reverb_echoes,
-        reverb_indexes,
+        reverb: Reverb::new(),
     };
 
     let host = cpal::default_host();
@@ -690,14 +902,6 @@ fn main() {
         .expect("no supported config?!")
         .with_sample_rate(SampleRate(SAMPLE_RATE))
         .config();
-    let mut echo_amps = Vec::with_capacity(reverb_echoes);
-    let first_echo = 1.0 / 4.0;
-    let mut echo_amp = first_echo;
-    for _i in 0..reverb_echoes {
-        echo_amps.push(echo_amp);
-        echo_amp *= 1.0 / 2.0;
-    }
-    let wet = 1.0 / 2.0;
     let stream = device
         .build_output_stream(
             &supported_config,
@@ -735,33 +939,9 @@ fn main() {
                         .as_ref()
                         .unwrap()
                         .samples[audio_thread.sample_index];
-                    let mut wet_sample = 0.0;
-                    for echo_index in 0..audio_thread.reverb_echoes {
-                        let reverb_sample = audio_thread.reverb[echo_index].as_ref().unwrap()
-                            [audio_thread.reverb_indexes[echo_index]];
-                        wet_sample += reverb_sample;
-                    }
-                    wet_sample *= wet;
-                    let final_sample = wet_sample + audio_sample * (1.0 - wet_sample);
+                    let final_sample = audio_thread.reverb.process(audio_sample);
                     //audio_sample *= 16.0;
                     *sample = Sample::from(&final_sample);
-                    for echo_index in 0..audio_thread.reverb_echoes {
-                        let echo_amp = echo_amps[echo_index];
-                        audio_thread.back_reverb[echo_index].as_mut().unwrap()
-                            [audio_thread.reverb_indexes[echo_index]] = final_sample * echo_amp;
-                    }
-                    let mut rev_len = audio_thread.reverb_length;
-                    for echo_index in 0..audio_thread.reverb_echoes {
-                        audio_thread.reverb_indexes[echo_index] += 1;
-                        if audio_thread.reverb_indexes[echo_index] >= rev_len {
-                            audio_thread.reverb_indexes[echo_index] = 0;
-                            let swap = audio_thread.reverb[echo_index].take();
-                            audio_thread.reverb[echo_index] =
-                                audio_thread.back_reverb[echo_index].take();
-                            audio_thread.back_reverb[echo_index] = swap;
-                        }
-                        rev_len /= 2;
-                    }
 
                     audio_thread.sample_index += 1;
                     if audio_thread.sample_index >= AUDIO_SAMPLES_LENGHT * 2 {
@@ -776,17 +956,7 @@ fn main() {
         .unwrap();
     stream.play().unwrap();
 
-    let mut processing_thread = ProcessingThread {
-        nutshell: Nutshell::new(
-            Vector2::new(VIDEO_SAMPLE_WIDTH as u32, VIDEO_SAMPLE_HEIGHT as u32),
-            LAYERS,
-            DEFINITION,
-        ),
-        primary: None,
-        process_receiver,
-        process_sender,
-        pointer: Vector2::new(0, 0),
-    };
+    let mut processing_thread = ProcessingThread::new(process_receiver, process_sender, gradient);
     println!("pre-processing");
     for i in 0..0 {
         println!("pre {}", i);
@@ -808,13 +978,53 @@ fn main() {
         last_update_instant: Instant::now(),
     };
 
+    // --fullscreen [monitor] opens borderless fullscreen, on the primary monitor by default
+    let (fullscreen, monitor) = match flag(&args, "--fullscreen") {
+        Some(values) => {
+            let monitor = values.first().map(|monitor| {
+                monitor
+                    .parse()
+                    .expect("--fullscreen monitor must be a number")
+            });
+            (Fullscreen::Borderless, monitor)
+        }
+        None => (Fullscreen::Off, None),
+    };
     let options = WindowOptions {
         title: String::from("space-time"),
         size: Vector2::new(1280, 720),
         graphics_size: Vector2::new(VIDEO_SAMPLE_WIDTH as u32, VIDEO_SAMPLE_HEIGHT as u32),
         update_delta: Duration::from_millis(16),
+        max_updates: 4,
+        redraw: Redraw::OnDemand,
+        filter: Filter::Nearest,
+        scaling: Scaling::Integer,
+        fullscreen,
+        monitor,
+        vsync: true,
+        resizable: true,
+        min_size: Some(Vector2::new(512, 256)),
     };
-    let window = Window::new(options, Box::new(window_thread));
+    let mut window = Window::new(options, Box::new(window_thread));
+    if let Some(index) = monitor {
+        if window.monitor() != monitor {
+            eprintln!("there is no monitor {}, using the primary one", index);
+        }
+    }
+    // --stats shows the frame timings from the start, F3 toggles them
+    window.show_stats(flag(&args, "--stats").is_some());
+    // --record run.gif for an animation, any other path for a directory of png files
+    if let Some(values) = flag(&args, "--record") {
+        let path = PathBuf::from(values.first().expect("--record needs a path"));
+        let recorder = match path.extension() {
+            Some(extension) if extension == "gif" => Recorder::gif(path, RECORD_SCALE),
+            _ => Recorder::png_sequence(path, RECORD_SCALE),
+        };
+        match recorder {
+            Ok(recorder) => window.start_recording(recorder),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
     thread::spawn(move || loop {
         let frame = processing_thread.primary.take().unwrap();
         processing_thread
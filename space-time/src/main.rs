@@ -1,8 +1,13 @@
+use std::env;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use uninutsh::{
     image::SpritesManager,
-    window::{EventHandler, Window, WindowEvent, WindowOptions},
+    pack::{self, EntryKind, Pack},
+    window::{
+        EventHandler, Filter, Fullscreen, Redraw, Scaling, Window, WindowEvent, WindowOptions,
+    },
     Vector2,
 };
 
@@ -10,45 +15,89 @@ struct Manager {
     sprites: SpritesManager,
 }
 
+const PACK: &str = "space-time.pack";
+
+fn sprites_directory() -> PathBuf {
+    ["resources", "sprites"].iter().collect()
+}
+
 impl Manager {
     fn new() -> Manager {
         let mut sprites = SpritesManager::new();
-        sprites.put(
-            "background0x0",
-            ["resources", "sprites", "background0x0.bmp"]
-                .iter()
-                .collect(),
-        );
+        // a pack beside the executable wins, otherwise the loose files under the working directory
+        match pack::beside_executable(PACK).filter(|path| path.is_file()) {
+            Some(path) => {
+                if let Err(error) = sprites.load_pack(path) {
+                    eprintln!("{}", error);
+                }
+            }
+            None => {
+                let loaded = sprites.try_put(
+                    "background0x0",
+                    sprites_directory().join("background0x0.bmp"),
+                );
+                if let Err(error) = loaded {
+                    eprintln!("{}", error);
+                }
+                sprites.watch(Duration::from_millis(500));
+            }
+        }
         Manager { sprites }
     }
 }
 
+fn write_pack(path: &Path) {
+    let mut pack = Pack::new();
+    pack.add_directory(EntryKind::Sprite, &sprites_directory(), &["bmp", "png"])
+        .expect("can not read the sprites directory");
+    pack.save(path).expect("can not write the pack");
+    println!("{} sprites packed into {:?}", pack.len(), path.as_os_str());
+}
+
 impl EventHandler for Manager {
     fn handle_event(&mut self, event: WindowEvent, window: &mut Window) {
         match event {
             WindowEvent::Exit => {
                 window.close();
             }
-            WindowEvent::Draw => {
+            WindowEvent::Draw(_) => {
                 let mut graphics = window.graphics().take().unwrap();
-                let sprite = self.sprites.get("background0x0");
-                graphics.put_sprite(sprite, 0, 0);
+                if let Ok(sprite) = self.sprites.try_get("background0x0") {
+                    graphics.put_sprite(sprite, 0, 0);
+                }
                 graphics.apply();
                 window.return_graphics(Some(graphics));
             }
-            WindowEvent::Update(_delta) => {
-                window.redraw();
+            WindowEvent::Update(delta) => {
+                for error in self.sprites.update(delta) {
+                    eprintln!("{}", error);
+                }
             }
+            _ => {}
         }
     }
 }
 
 fn main() {
+    if env::args().nth(1).as_deref() == Some("--pack") {
+        let path = pack::beside_executable(PACK).expect("can not find the executable directory");
+        write_pack(&path);
+        return;
+    }
     let window_options = WindowOptions {
         title: String::from("space-time"),
         size: Vector2::new(1280, 720),
         graphics_size: Vector2::new(512, 256),
         update_delta: Duration::from_millis(16),
+        max_updates: 4,
+        redraw: Redraw::EveryUpdate,
+        filter: Filter::Nearest,
+        scaling: Scaling::Fit,
+        fullscreen: Fullscreen::Off,
+        monitor: None,
+        vsync: true,
+        resizable: true,
+        min_size: Some(Vector2::new(512, 256)),
     };
     let window = Window::new(window_options, Box::new(Manager::new()));
     window.event_loop();
@@ -0,0 +1,129 @@
+use super::{sheet::SpriteSheet, DrawOptions, Graphics, Sprite};
+use crate::{Rectangle, Vector2};
+
+pub struct Camera {
+    pub position: Vector2<i32>,
+    zoom: u32,
+}
+
+impl Camera {
+    pub fn new(x: i32, y: i32) -> Camera {
+        Camera {
+            position: Vector2::new(x, y),
+            zoom: 1,
+        }
+    }
+    pub fn zoom(&self) -> u32 {
+        self.zoom
+    }
+    pub fn set_zoom(&mut self, zoom: u32) {
+        self.zoom = zoom.max(1);
+    }
+    pub fn move_by(&mut self, x: i32, y: i32) {
+        self.position.x += x;
+        self.position.y += y;
+    }
+    pub fn center_on(&mut self, x: i32, y: i32, graphics: &Graphics) {
+        let view = self.view(graphics);
+        self.position.x = x - view.size.x / 2;
+        self.position.y = y - view.size.y / 2;
+    }
+    // The world area that ends up on the graphics.
+    pub fn view(&self, graphics: &Graphics) -> Rectangle<i32> {
+        let zoom = self.zoom as i32;
+        let width = (graphics.width() as i32 + zoom - 1) / zoom;
+        let height = (graphics.height() as i32 + zoom - 1) / zoom;
+        Rectangle::new(self.position.x, self.position.y, width, height)
+    }
+    pub fn to_screen(&self, x: i32, y: i32) -> Vector2<i32> {
+        let zoom = self.zoom as i32;
+        Vector2::new((x - self.position.x) * zoom, (y - self.position.y) * zoom)
+    }
+    pub fn to_world(&self, x: i32, y: i32) -> Vector2<i32> {
+        let zoom = self.zoom as i32;
+        Vector2::new(
+            x.div_euclid(zoom) + self.position.x,
+            y.div_euclid(zoom) + self.position.y,
+        )
+    }
+    pub fn draw_pixel(&self, graphics: &mut Graphics, x: i32, y: i32) {
+        let screen = self.to_screen(x, y);
+        if self.zoom == 1 {
+            graphics.draw_pixel(screen.x, screen.y);
+        } else {
+            graphics.fill_rectangle(screen.x, screen.y, self.zoom, self.zoom);
+        }
+    }
+    pub fn fill_rectangle(&self, graphics: &mut Graphics, x: i32, y: i32, width: u32, height: u32) {
+        let screen = self.to_screen(x, y);
+        graphics.fill_rectangle(screen.x, screen.y, width * self.zoom, height * self.zoom);
+    }
+    pub fn draw_sprite(&self, graphics: &mut Graphics, sprite: &Sprite, x: i32, y: i32) {
+        let screen = self.to_screen(x, y);
+        if self.zoom == 1 {
+            graphics.draw_sprite(sprite, screen.x, screen.y);
+        } else {
+            let options = self.options();
+            graphics.put_sprite_with(sprite, screen.x, screen.y, &options);
+        }
+    }
+    pub fn draw_frame(
+        &self,
+        graphics: &mut Graphics,
+        sheet: &SpriteSheet,
+        frame: usize,
+        x: i32,
+        y: i32,
+    ) {
+        let screen = self.to_screen(x, y);
+        if self.zoom == 1 {
+            graphics.draw_frame(sheet, frame, screen.x, screen.y);
+        } else if let Some(region) = sheet.region(frame) {
+            let mut options = self.options();
+            options.region = Some(region);
+            graphics.put_sprite_with(sheet.sprite(), screen.x, screen.y, &options);
+        }
+    }
+    fn options(&self) -> DrawOptions {
+        DrawOptions {
+            scale: Vector2::new(self.zoom as f64, self.zoom as f64),
+            ..DrawOptions::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_covers_the_graphics() {
+        let graphics = Graphics::new(10, 6);
+        let mut camera = Camera::new(-3, 4);
+        let view = camera.view(&graphics);
+        assert_eq!(
+            (view.position.x, view.position.y, view.size.x, view.size.y),
+            (-3, 4, 10, 6)
+        );
+        // a partly visible world pixel still counts
+        camera.set_zoom(4);
+        let view = camera.view(&graphics);
+        assert_eq!((view.size.x, view.size.y), (3, 2));
+        camera.center_on(0, 0, &graphics);
+        assert_eq!((camera.position.x, camera.position.y), (-1, -1));
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        let mut camera = Camera::new(5, -2);
+        camera.set_zoom(3);
+        let screen = camera.to_screen(7, 0);
+        assert_eq!((screen.x, screen.y), (6, 6));
+        let world = camera.to_world(8, 8);
+        assert_eq!((world.x, world.y), (7, 0));
+        let world = camera.to_world(-1, 0);
+        assert_eq!((world.x, world.y), (4, -2));
+        camera.set_zoom(0);
+        assert_eq!(camera.zoom(), 1);
+    }
+}
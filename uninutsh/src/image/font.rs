@@ -0,0 +1,262 @@
+use super::{Error, Sprite};
+use crate::Vector2;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// 5x7 glyphs for ' '..='~', one byte per column with the top row in the lowest bit
+const DEFAULT_GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08],
+    [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x01, 0x01],
+    [0x3E, 0x41, 0x41, 0x51, 0x32],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x04, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x7F, 0x20, 0x18, 0x20, 0x7F],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x03, 0x04, 0x78, 0x04, 0x03],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x7F, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7E, 0x09, 0x01, 0x02],
+    [0x08, 0x14, 0x54, 0x54, 0x3C],
+    [0x7F, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x00, 0x7F, 0x10, 0x28, 0x44],
+    [0x00, 0x41, 0x7F, 0x40, 0x00],
+    [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C],
+    [0x7C, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20],
+    [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+pub(crate) struct Glyph {
+    lit: Vec<bool>,
+}
+
+pub struct Font {
+    glyphs: HashMap<char, Glyph>,
+    glyph_size: Vector2<u32>,
+    spacing: Vector2<u32>,
+}
+
+impl Default for Font {
+    fn default() -> Font {
+        Font::new()
+    }
+}
+
+impl Font {
+    pub fn new() -> Font {
+        let glyph_size = Vector2::new(5, 7);
+        let mut glyphs = HashMap::with_capacity(DEFAULT_GLYPHS.len());
+        for (index, columns) in DEFAULT_GLYPHS.iter().enumerate() {
+            let mut lit = Vec::with_capacity(glyph_size.x as usize * glyph_size.y as usize);
+            for y in 0..glyph_size.y {
+                for x in 0..glyph_size.x {
+                    lit.push((columns[x as usize] >> y) & 1 == 1);
+                }
+            }
+            glyphs.insert((b' ' + index as u8) as char, Glyph { lit });
+        }
+        Font {
+            glyphs,
+            glyph_size,
+            spacing: Vector2::new(1, 1),
+        }
+    }
+    pub fn load(path: PathBuf, glyph_size: Vector2<u32>, first: char) -> Font {
+        Font::from_sprite(&Sprite::load(path), glyph_size, first)
+    }
+    pub fn try_load(path: PathBuf, glyph_size: Vector2<u32>, first: char) -> Result<Font, Error> {
+        let sheet = Sprite::try_load(path)?;
+        Ok(Font::from_sprite(&sheet, glyph_size, first))
+    }
+    // The sheet is a grid of `glyph_size` cells holding consecutive characters
+    // from `first`, row by row; bright opaque pixels are the lit ones.
+    // Panics on a glyph size of zero.
+    pub fn from_sprite(sheet: &Sprite, glyph_size: Vector2<u32>, first: char) -> Font {
+        assert!(
+            glyph_size.x > 0 && glyph_size.y > 0,
+            "glyph size must not be zero, got {}x{}",
+            glyph_size.x,
+            glyph_size.y
+        );
+        let columns = sheet.width() / glyph_size.x;
+        let rows = sheet.height() / glyph_size.y;
+        let mut glyphs = HashMap::with_capacity(columns as usize * rows as usize);
+        for index in 0..columns * rows {
+            let character = match char::from_u32(first as u32 + index) {
+                Some(character) => character,
+                None => break,
+            };
+            let left = index % columns * glyph_size.x;
+            let top = index / columns * glyph_size.y;
+            let mut lit = Vec::with_capacity(glyph_size.x as usize * glyph_size.y as usize);
+            for y in 0..glyph_size.y {
+                for x in 0..glyph_size.x {
+                    let pixel = sheet.get(left + x, top + y);
+                    let luma = (pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32) / 3;
+                    lit.push(pixel[3] >= 128 && luma >= 128);
+                }
+            }
+            glyphs.insert(character, Glyph { lit });
+        }
+        Font {
+            glyphs,
+            glyph_size,
+            spacing: Vector2::new(1, 1),
+        }
+    }
+    pub fn set_spacing(&mut self, spacing: Vector2<u32>) {
+        self.spacing = spacing;
+    }
+    pub fn glyph_size(&self) -> Vector2<u32> {
+        self.glyph_size
+    }
+    pub fn advance(&self) -> u32 {
+        self.glyph_size.x + self.spacing.x
+    }
+    pub fn line_height(&self) -> u32 {
+        self.glyph_size.y + self.spacing.y
+    }
+    pub fn text_width(&self, text: &str) -> u32 {
+        self.measure(text).x
+    }
+    pub fn measure(&self, text: &str) -> Vector2<u32> {
+        let mut width = 0;
+        let mut lines = 0;
+        for line in text.split('\n') {
+            let characters = line.chars().count() as u32;
+            if characters > 0 {
+                let line_width = characters * self.advance() - self.spacing.x;
+                width = width.max(line_width);
+            }
+            lines += 1;
+        }
+        let height = lines * self.line_height() - self.spacing.y;
+        Vector2::new(width, height)
+    }
+    pub(crate) fn glyph(&self, character: char) -> Option<&Glyph> {
+        match self.glyphs.get(&character) {
+            Some(glyph) => Some(glyph),
+            None => self.glyphs.get(&'?'),
+        }
+    }
+    pub(crate) fn is_lit(&self, glyph: &Glyph, x: u32, y: u32) -> bool {
+        glyph.lit[(y * self.glyph_size.x + x) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Color;
+
+    #[test]
+    fn measure_counts_the_longest_line() {
+        let font = Font::new();
+        assert_eq!(font.text_width(""), 0);
+        assert_eq!(font.text_width("a"), 5);
+        assert_eq!(font.text_width("abc"), 17);
+        let size = font.measure("ab\nabcd\n");
+        assert_eq!((size.x, size.y), (23, 23));
+        let mut wide = Font::new();
+        wide.set_spacing(Vector2::new(3, 0));
+        let size = wide.measure("ab");
+        assert_eq!((size.x, size.y), (13, 7));
+    }
+
+    #[test]
+    fn sprites_are_cut_into_glyphs() {
+        let mut sheet = Sprite::new(6, 2);
+        sheet.fill_rectangle(0, 0, 6, 2, Color::from_rgb(0, 0, 0));
+        sheet.put(4, 1, Color::from_rgb(255, 255, 255));
+        let font = Font::from_sprite(&sheet, Vector2::new(2, 2), 'A');
+        assert_eq!(font.glyphs.len(), 3);
+        let glyph = font.glyph('C').unwrap();
+        assert!(font.is_lit(glyph, 0, 1));
+        assert!(!font.is_lit(glyph, 1, 1));
+        assert!(font.glyph('D').is_none());
+        let size = font.measure("AB");
+        assert_eq!((size.x, size.y), (5, 2));
+    }
+
+    #[test]
+    #[should_panic(expected = "glyph size must not be zero")]
+    fn zero_glyph_size_panics() {
+        Font::from_sprite(&Sprite::new(6, 2), Vector2::new(2, 0), 'A');
+    }
+}
@@ -1,21 +1,251 @@
-use super::{Color, Sprite};
-use image::{imageops, io::Reader, Rgba, RgbaImage};
-use imageproc::{drawing, rect::Rect};
-use palette::{FromColor, Hsv, Srgb};
+use super::{font::Font, BlendMode, Color, ColorSpace, DrawOptions, Error, Rotation, Sprite};
+use crate::{Rectangle, Vector2};
+use image::{
+    imageops::{self, FilterType},
+    io::Reader,
+    GenericImageView, Rgba, RgbaImage,
+};
+use imageproc::{
+    drawing::{self, Canvas},
+    point::Point,
+    rect::Rect,
+};
+use palette::{encoding, white_point::D65, FromColor, Hsl, Hsv, Lab, LinSrgb, Srgb};
+use std::collections::HashSet;
 use std::path::PathBuf;
 
+#[derive(Clone)]
 pub struct Data {
     buffer: RgbaImage,
+    mode: BlendMode,
+    clip: Option<Rectangle<i32>>,
+    // what changed since the last `update`
+    dirty: Option<Rectangle<i32>>,
 }
 
 pub fn hsb_to_rgb(hsb: [f64; 3]) -> [u8; 3] {
     let internal_hsv = Hsv::new(hsb[0], hsb[1], hsb[2]);
     let internal_rgb = Srgb::from_color(internal_hsv);
-    [
-        (internal_rgb.red * 255.) as u8,
-        (internal_rgb.green * 255.) as u8,
-        (internal_rgb.blue * 255.) as u8,
-    ]
+    to_bytes(internal_rgb)
+}
+
+fn srgb(rgb: [u8; 3]) -> Srgb<f64> {
+    Srgb::new(
+        rgb[0] as f64 / 255.,
+        rgb[1] as f64 / 255.,
+        rgb[2] as f64 / 255.,
+    )
+}
+
+fn to_bytes(color: Srgb<f64>) -> [u8; 3] {
+    let byte = |value: f64| (value.clamp(0., 1.) * 255.).round() as u8;
+    [byte(color.red), byte(color.green), byte(color.blue)]
+}
+
+pub fn rgb_to_hsb(rgb: [u8; 3]) -> [f64; 3] {
+    let hsv = Hsv::from_color(srgb(rgb));
+    [hsv.hue.to_positive_degrees(), hsv.saturation, hsv.value]
+}
+
+pub fn linear_to_rgb(linear: [f64; 3]) -> [u8; 3] {
+    let linear = LinSrgb::new(linear[0], linear[1], linear[2]);
+    to_bytes(Srgb::from_linear(linear))
+}
+
+pub fn rgb_to_linear(rgb: [u8; 3]) -> [f64; 3] {
+    let linear = srgb(rgb).into_linear();
+    [linear.red, linear.green, linear.blue]
+}
+
+pub fn hsl_to_rgb(hsl: [f64; 3]) -> [u8; 3] {
+    to_bytes(Srgb::from_color(Hsl::new(hsl[0], hsl[1], hsl[2])))
+}
+
+pub fn rgb_to_hsl(rgb: [u8; 3]) -> [f64; 3] {
+    let hsl: Hsl<encoding::Srgb, f64> = Hsl::from_color(srgb(rgb));
+    [hsl.hue.to_positive_degrees(), hsl.saturation, hsl.lightness]
+}
+
+pub fn lab_to_rgb(lab: [f64; 3]) -> [u8; 3] {
+    let lab: Lab<D65, f64> = Lab::new(lab[0], lab[1], lab[2]);
+    to_bytes(Srgb::from_color(lab))
+}
+
+pub fn rgb_to_lab(rgb: [u8; 3]) -> [f64; 3] {
+    let lab: Lab<D65, f64> = Lab::from_color(srgb(rgb));
+    [lab.l, lab.a, lab.b]
+}
+
+// "#rgb", "#rgba", "#rrggbb" or "#rrggbbaa", the '#' is optional
+pub fn parse_hex(hex: &str) -> Option<[u8; 4]> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    let values: Vec<u8> = digits
+        .chars()
+        .map(|digit| digit.to_digit(16).map(|value| value as u8))
+        .collect::<Option<_>>()?;
+    let short = |index: usize| values[index] * 17;
+    let long = |index: usize| values[index * 2] * 16 + values[index * 2 + 1];
+    match values.len() {
+        3 => Some([short(0), short(1), short(2), 255]),
+        4 => Some([short(0), short(1), short(2), short(3)]),
+        6 => Some([long(0), long(1), long(2), 255]),
+        8 => Some([long(0), long(1), long(2), long(3)]),
+        _ => None,
+    }
+}
+
+pub fn mix(from: Color, to: Color, t: f64, space: ColorSpace) -> Color {
+    let lerp = |from: f64, to: f64| from + (to - from) * t;
+    let lerp3 = |from: [f64; 3], to: [f64; 3]| {
+        [
+            lerp(from[0], to[0]),
+            lerp(from[1], to[1]),
+            lerp(from[2], to[2]),
+        ]
+    };
+    let byte = |from: u8, to: u8| lerp(from as f64, to as f64).round().clamp(0., 255.) as u8;
+    let alpha = byte(from.alpha, to.alpha);
+    match space {
+        ColorSpace::Rgb => {
+            let red = byte(from.rgb[0], to.rgb[0]);
+            let green = byte(from.rgb[1], to.rgb[1]);
+            let blue = byte(from.rgb[2], to.rgb[2]);
+            Color::from_rgba(red, green, blue, alpha)
+        }
+        ColorSpace::Linear => Color::from_linear(lerp3(from.linear(), to.linear()), alpha),
+        ColorSpace::Hsb => {
+            // greys, black and white take the other end's hue, unless both are grey
+            let grey = |color: Color| color.hsb[1] <= 0. || color.hsb[2] <= 0.;
+            let (from_hue, to_hue) = match (grey(from), grey(to)) {
+                (true, false) => (to.hsb[0], to.hsb[0]),
+                (false, true) => (from.hsb[0], from.hsb[0]),
+                _ => (from.hsb[0], to.hsb[0]),
+            };
+            let mut turn = (to_hue - from_hue).rem_euclid(360.);
+            if turn > 180. {
+                turn -= 360.;
+            }
+            let hue = from_hue + turn * t;
+            let saturation = lerp(from.hsb[1], to.hsb[1]);
+            let brightness = lerp(from.hsb[2], to.hsb[2]);
+            Color::from_hsb([hue, saturation, brightness], alpha)
+        }
+        ColorSpace::Lab => Color::from_lab(lerp3(from.lab(), to.lab()), alpha),
+    }
+}
+
+// Grows `area` to also cover `region`, empty regions leave it as it is.
+pub fn include(area: &mut Option<Rectangle<i32>>, region: Rectangle<i32>) {
+    if region.size.x <= 0 || region.size.y <= 0 {
+        return;
+    }
+    *area = match *area {
+        Some(current) => {
+            let right = |area: Rectangle<i32>| area.position.x + area.size.x;
+            let bottom = |area: Rectangle<i32>| area.position.y + area.size.y;
+            let left = current.position.x.min(region.position.x);
+            let top = current.position.y.min(region.position.y);
+            let width = right(current).max(right(region)) - left;
+            let height = bottom(current).max(bottom(region)) - top;
+            Some(Rectangle::new(left, top, width, height))
+        }
+        None => Some(region),
+    };
+}
+
+fn rgba(color: Color) -> Rgba<u8> {
+    Rgba([color.rgb[0], color.rgb[1], color.rgb[2], color.alpha])
+}
+
+// Separable blending followed by source-over compositing, as in the W3C
+// compositing spec, on straight (not premultiplied) alpha.
+fn blend(mode: BlendMode, bottom: &mut Rgba<u8>, top: Rgba<u8>) {
+    let function: fn(f32, f32) -> f32 = match mode {
+        BlendMode::Replace => {
+            *bottom = top;
+            return;
+        }
+        BlendMode::Normal => |_b, t| t,
+        BlendMode::Additive => |b, t| (b + t).min(1.0),
+        BlendMode::Multiply => |b, t| b * t,
+        BlendMode::Screen => |b, t| 1.0 - (1.0 - b) * (1.0 - t),
+    };
+    let top_alpha = top[3] as f32 / 255.0;
+    let bottom_alpha = bottom[3] as f32 / 255.0;
+    let alpha = top_alpha + bottom_alpha * (1.0 - top_alpha);
+    if alpha <= 0.0 {
+        return;
+    }
+    for channel in 0..3 {
+        let b = bottom[channel] as f32 / 255.0;
+        let t = top[channel] as f32 / 255.0;
+        let mixed = (1.0 - bottom_alpha) * t + bottom_alpha * function(b, t);
+        let value = (top_alpha * mixed + bottom_alpha * b * (1.0 - top_alpha)) / alpha;
+        bottom[channel] = (value * 255.0).round() as u8;
+    }
+    bottom[3] = (alpha * 255.0).round() as u8;
+}
+
+// Alpha blends every drawn pixel, optionally only once per pixel so outlines
+// whose segments share their ends do not darken the corners.
+struct Blender<'a> {
+    buffer: &'a mut RgbaImage,
+    mode: BlendMode,
+    bounds: Rectangle<i32>,
+    drawn: Option<HashSet<(u32, u32)>>,
+    dirty: &'a mut Option<Rectangle<i32>>,
+}
+
+impl<'a> Blender<'a> {
+    fn new(data: &'a mut Data) -> Blender<'a> {
+        let bounds = data.bounds();
+        Blender {
+            buffer: &mut data.buffer,
+            mode: data.mode,
+            bounds,
+            drawn: None,
+            dirty: &mut data.dirty,
+        }
+    }
+    fn once(data: &'a mut Data) -> Blender<'a> {
+        let mut blender = Blender::new(data);
+        blender.drawn = Some(HashSet::new());
+        blender
+    }
+}
+
+impl<'a> Canvas for Blender<'a> {
+    type Pixel = Rgba<u8>;
+    fn dimensions(&self) -> (u32, u32) {
+        self.buffer.dimensions()
+    }
+    fn get_pixel(&self, x: u32, y: u32) -> Rgba<u8> {
+        *self.buffer.get_pixel(x, y)
+    }
+    fn draw_pixel(&mut self, x: u32, y: u32, color: Rgba<u8>) {
+        if !self.bounds.contains(x as i32, y as i32) {
+            return;
+        }
+        if let Some(drawn) = &mut self.drawn {
+            if !drawn.insert((x, y)) {
+                return;
+            }
+        }
+        blend(self.mode, self.buffer.get_pixel_mut(x, y), color);
+        include(self.dirty, Rectangle::new(x as i32, y as i32, 1, 1));
+    }
+}
+
+fn points(vertices: &[Vector2<i32>]) -> Vec<Point<i32>> {
+    let mut points: Vec<Point<i32>> = vertices
+        .iter()
+        .map(|vertex| Point::new(vertex.x, vertex.y))
+        .collect();
+    // imageproc wants an open path
+    while points.len() > 1 && points[0] == points[points.len() - 1] {
+        points.pop();
+    }
+    points
 }
 
 impl Data {
@@ -26,15 +256,65 @@ impl Data {
             Rect::at(0, 0).of_size(width, height),
             Rgba([255, 255, 255, 255]),
         );
-        Data { buffer }
+        let mode = BlendMode::Normal;
+        let mut data = Data {
+            buffer,
+            mode,
+            clip: None,
+            dirty: None,
+        };
+        data.touch_all();
+        data
+    }
+    pub fn try_load(path: PathBuf) -> Result<Data, Error> {
+        let reader = match Reader::open(&path) {
+            Ok(reader) => reader,
+            Err(error) => return Err(Error::Open(path, error)),
+        };
+        let buffer = match reader.decode() {
+            Ok(image) => image.to_rgba8(),
+            Err(error) => return Err(Error::Decode(path, error)),
+        };
+        let mode = BlendMode::Normal;
+        let mut data = Data {
+            buffer,
+            mode,
+            clip: None,
+            dirty: None,
+        };
+        data.touch_all();
+        Ok(data)
     }
-    pub fn load(path: PathBuf) -> Data {
-        let buffer = Reader::open(&path)
-            .expect(format!("open at {:?}", path.as_os_str()).as_str())
-            .decode()
-            .expect(format!("decode at {:?}", path.as_os_str()).as_str())
-            .to_rgba8();
-        Data { buffer }
+    pub fn from_memory(bytes: &[u8]) -> Result<Data, image::ImageError> {
+        let buffer = image::load_from_memory(bytes)?.to_rgba8();
+        let mode = BlendMode::Normal;
+        let mut data = Data {
+            buffer,
+            mode,
+            clip: None,
+            dirty: None,
+        };
+        data.touch_all();
+        Ok(data)
+    }
+    // nearest neighbour, so every pixel becomes a scale by scale block
+    pub fn scaled(&self, scale: u32) -> RgbaImage {
+        let scale = scale.max(1);
+        if scale == 1 {
+            return self.buffer.clone();
+        }
+        imageops::resize(
+            &self.buffer,
+            self.buffer.width() * scale,
+            self.buffer.height() * scale,
+            FilterType::Nearest,
+        )
+    }
+    pub fn save(&self, path: PathBuf, scale: u32) -> Result<(), Error> {
+        match self.scaled(scale).save(&path) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(Error::Encode(path, error)),
+        }
     }
     pub fn width(&self) -> u32 {
         self.buffer.width()
@@ -42,28 +322,539 @@ impl Data {
     pub fn height(&self) -> u32 {
         self.buffer.height()
     }
-    pub fn update(&mut self, pixels: Option<Vec<u8>>) -> Option<Vec<u8>> {
-        match pixels {
-            Some(mut vector) => {
-                for y in 0..self.height() as usize {
-                    let iy = self.height() as usize - y - 1;
-                    for x in 0..self.width() as usize {
-                        let pixel = self.buffer.get_pixel(x as u32, y as u32).0;
-                        vector[iy * self.width() as usize * 4 + x * 4 + 0] = pixel[0];
-                        vector[iy * self.width() as usize * 4 + x * 4 + 1] = pixel[1];
-                        vector[iy * self.width() as usize * 4 + x * 4 + 2] = pixel[2];
-                        vector[iy * self.width() as usize * 4 + x * 4 + 3] = pixel[3];
+    pub fn touch_all(&mut self) {
+        let area = Rectangle::new(0, 0, self.width() as i32, self.height() as i32);
+        include(&mut self.dirty, area);
+    }
+    // Copies what changed into `pixels`, upside down for GL, and returns the region,
+    // in buffer coordinates.
+    pub fn update(&mut self, pixels: &mut [u8]) -> Option<Rectangle<i32>> {
+        let dirty = self.dirty.take()?;
+        let width = self.width() as usize;
+        let height = self.height() as usize;
+        let left = dirty.position.x as usize;
+        let right = left + dirty.size.x as usize;
+        for y in dirty.position.y as usize..(dirty.position.y + dirty.size.y) as usize {
+            let iy = height - y - 1;
+            let row = &self.buffer.as_raw()[(y * width + left) * 4..(y * width + right) * 4];
+            pixels[(iy * width + left) * 4..(iy * width + right) * 4].copy_from_slice(row);
+        }
+        Some(dirty)
+    }
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.mode = mode;
+    }
+    pub fn blend_mode(&self) -> BlendMode {
+        self.mode
+    }
+    pub fn set_clip(&mut self, clip: Option<Rectangle<i32>>) {
+        self.clip = clip;
+    }
+    pub fn clip(&self) -> Option<Rectangle<i32>> {
+        self.clip
+    }
+    // The part of the buffer that drawing may touch: all of it, cut down by the clip.
+    fn bounds(&self) -> Rectangle<i32> {
+        let mut left = 0;
+        let mut top = 0;
+        let mut right = self.width() as i32;
+        let mut bottom = self.height() as i32;
+        if let Some(clip) = self.clip {
+            left = left.max(clip.position.x);
+            top = top.max(clip.position.y);
+            right = right.min(clip.position.x.saturating_add(clip.size.x));
+            bottom = bottom.min(clip.position.y.saturating_add(clip.size.y));
+        }
+        Rectangle::new(left, top, (right - left).max(0), (bottom - top).max(0))
+    }
+    // Marks the columns `left..right` of the rows `top..bottom` as changed.
+    fn touch(&mut self, left: i32, top: i32, right: i32, bottom: i32) {
+        let area = Rectangle::new(left, top, right - left, bottom - top);
+        include(&mut self.dirty, area);
+    }
+    fn overlay<I>(&mut self, top: &I, x: i32, y: i32)
+    where
+        I: GenericImageView<Pixel = Rgba<u8>>,
+    {
+        let bounds = self.bounds();
+        let top_width = top.width() as i32;
+        let top_height = top.height() as i32;
+        let right = bounds.position.x + bounds.size.x;
+        let bottom = bounds.position.y + bounds.size.y;
+        // saturating, x and y may be anywhere
+        let rows =
+            0.max(bounds.position.y.saturating_sub(y))..top_height.min(bottom.saturating_sub(y));
+        let columns =
+            0.max(bounds.position.x.saturating_sub(x))..top_width.min(right.saturating_sub(x));
+        if rows.is_empty() || columns.is_empty() {
+            return;
+        }
+        self.touch(
+            x + columns.start,
+            y + rows.start,
+            x + columns.end,
+            y + rows.end,
+        );
+        for top_y in rows {
+            for top_x in columns.clone() {
+                let pixel = top.get_pixel(top_x as u32, top_y as u32);
+                let target = self
+                    .buffer
+                    .get_pixel_mut((x + top_x) as u32, (y + top_y) as u32);
+                blend(self.mode, target, pixel);
+            }
+        }
+    }
+    pub fn put_sprite(&mut self, sprite: &Sprite, x: i32, y: i32) {
+        self.overlay(&sprite.internals.buffer, x, y)
+    }
+    pub fn put_sprite_region(&mut self, sprite: &Sprite, region: Rectangle<u32>, x: i32, y: i32) {
+        let source = &sprite.internals.buffer;
+        let left = region.position.x.min(source.width());
+        let top = region.position.y.min(source.height());
+        let width = region.size.x.min(source.width() - left);
+        let height = region.size.y.min(source.height() - top);
+        let view = source.view(left, top, width, height);
+        self.overlay(&view, x, y)
+    }
+    pub fn put_sprite_with(&mut self, sprite: &Sprite, x: i32, y: i32, options: &DrawOptions) {
+        let source = &sprite.internals.buffer;
+        let region = match options.region {
+            Some(region) => region,
+            None => Rectangle::new(0, 0, source.width(), source.height()),
+        };
+        let left = region.position.x.min(source.width());
+        let top = region.position.y.min(source.height());
+        let width = region.size.x.min(source.width() - left);
+        let height = region.size.y.min(source.height() - top);
+        if width == 0 || height == 0 || options.scale.x <= 0.0 || options.scale.y <= 0.0 {
+            return;
+        }
+        let (rotated_width, rotated_height) = match options.rotation {
+            Rotation::None | Rotation::Half => (width, height),
+            Rotation::Clockwise90 | Rotation::Counterclockwise90 => (height, width),
+        };
+        let target_width = (rotated_width as f64 * options.scale.x).round() as i32;
+        let target_height = (rotated_height as f64 * options.scale.y).round() as i32;
+        let tint = rgba(options.tint);
+        let opacity = options.opacity.clamp(0.0, 1.0);
+        let bounds = self.bounds();
+        let right = bounds.position.x + bounds.size.x;
+        let bottom = bounds.position.y + bounds.size.y;
+        let rows =
+            0.max(bounds.position.y.saturating_sub(y))..target_height.min(bottom.saturating_sub(y));
+        let columns =
+            0.max(bounds.position.x.saturating_sub(x))..target_width.min(right.saturating_sub(x));
+        if rows.is_empty() || columns.is_empty() {
+            return;
+        }
+        self.touch(
+            x + columns.start,
+            y + rows.start,
+            x + columns.end,
+            y + rows.end,
+        );
+        for target_y in rows {
+            for target_x in columns.clone() {
+                // nearest neighbour back through the flips, then the rotation
+                let mut rotated_x =
+                    ((target_x as f64 / options.scale.x) as u32).min(rotated_width - 1);
+                let mut rotated_y =
+                    ((target_y as f64 / options.scale.y) as u32).min(rotated_height - 1);
+                if options.flip_x {
+                    rotated_x = rotated_width - 1 - rotated_x;
+                }
+                if options.flip_y {
+                    rotated_y = rotated_height - 1 - rotated_y;
+                }
+                let (source_x, source_y) = match options.rotation {
+                    Rotation::None => (rotated_x, rotated_y),
+                    Rotation::Clockwise90 => (rotated_y, height - 1 - rotated_x),
+                    Rotation::Half => (width - 1 - rotated_x, height - 1 - rotated_y),
+                    Rotation::Counterclockwise90 => (width - 1 - rotated_y, rotated_x),
+                };
+                let mut pixel = *source.get_pixel(left + source_x, top + source_y);
+                for channel in 0..4 {
+                    pixel[channel] = (pixel[channel] as u32 * tint[channel] as u32 / 255) as u8;
+                }
+                pixel[3] = (pixel[3] as f64 * opacity).round() as u8;
+                let bottom = self
+                    .buffer
+                    .get_pixel_mut((x + target_x) as u32, (y + target_y) as u32);
+                blend(self.mode, bottom, pixel);
+            }
+        }
+    }
+    pub fn put(&mut self, x: i32, y: i32, color: Color) {
+        if !self.bounds().contains(x, y) {
+            return;
+        }
+        let target = self.buffer.get_pixel_mut(x as u32, y as u32);
+        blend(self.mode, target, rgba(color));
+        self.touch(x, y, x + 1, y + 1);
+    }
+    // the caller may write anywhere
+    pub fn buffer_mut(&mut self) -> &mut RgbaImage {
+        self.touch_all();
+        &mut self.buffer
+    }
+    pub fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        self.buffer.get_pixel(x, y).0
+    }
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        let mut canvas = Blender::new(self);
+        drawing::draw_line_segment_mut(
+            &mut canvas,
+            (x0 as f32, y0 as f32),
+            (x1 as f32, y1 as f32),
+            rgba(color),
+        );
+    }
+    pub fn draw_rectangle(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let mut canvas = Blender::once(self);
+        let rect = Rect::at(x, y).of_size(width, height);
+        drawing::draw_hollow_rect_mut(&mut canvas, rect, rgba(color));
+    }
+    pub fn fill_rectangle(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let mut canvas = Blender::new(self);
+        let rect = Rect::at(x, y).of_size(width, height);
+        drawing::draw_filled_rect_mut(&mut canvas, rect, rgba(color));
+    }
+    pub fn draw_ellipse(&mut self, x: i32, y: i32, radius_x: i32, radius_y: i32, color: Color) {
+        let mut canvas = Blender::once(self);
+        drawing::draw_hollow_ellipse_mut(&mut canvas, (x, y), radius_x, radius_y, rgba(color));
+    }
+    pub fn fill_ellipse(&mut self, x: i32, y: i32, radius_x: i32, radius_y: i32, color: Color) {
+        let mut canvas = Blender::once(self);
+        drawing::draw_filled_ellipse_mut(&mut canvas, (x, y), radius_x, radius_y, rgba(color));
+    }
+    pub fn draw_polygon(&mut self, vertices: &[Vector2<i32>], color: Color) {
+        let points = points(vertices);
+        let mut canvas = Blender::once(self);
+        for i in 0..points.len() {
+            let start = points[i];
+            let end = points[(i + 1) % points.len()];
+            drawing::draw_line_segment_mut(
+                &mut canvas,
+                (start.x as f32, start.y as f32),
+                (end.x as f32, end.y as f32),
+                rgba(color),
+            );
+        }
+    }
+    pub fn fill_polygon(&mut self, vertices: &[Vector2<i32>], color: Color) {
+        let points = points(vertices);
+        // less than a triangle has no area, and imageproc panics on it
+        let mut distinct: Vec<(i32, i32)> = points.iter().map(|point| (point.x, point.y)).collect();
+        distinct.sort_unstable();
+        distinct.dedup();
+        if distinct.len() < 3 {
+            return;
+        }
+        let mut canvas = Blender::once(self);
+        drawing::draw_polygon_mut(&mut canvas, &points, rgba(color));
+    }
+    pub fn flood_fill(&mut self, x: i32, y: i32, color: Color) {
+        let bounds = self.bounds();
+        if !bounds.contains(x, y) {
+            return;
+        }
+        let target = *self.buffer.get_pixel(x as u32, y as u32);
+        let mut replacement = target;
+        blend(self.mode, &mut replacement, rgba(color));
+        if replacement == target {
+            return;
+        }
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            if !bounds.contains(x, y) || *self.buffer.get_pixel(x as u32, y as u32) != target {
+                continue;
+            }
+            self.buffer.put_pixel(x as u32, y as u32, replacement);
+            self.touch(x, y, x + 1, y + 1);
+            stack.push((x - 1, y));
+            stack.push((x, y - 1));
+            stack.push((x + 1, y));
+            stack.push((x, y + 1));
+        }
+    }
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, font: &Font, color: Color) {
+        let glyph_size = font.glyph_size();
+        let advance = font.advance() as i32;
+        let mut top = y;
+        for line in text.split('\n') {
+            let mut left = x;
+            for character in line.chars() {
+                if let Some(glyph) = font.glyph(character) {
+                    for glyph_y in 0..glyph_size.y {
+                        for glyph_x in 0..glyph_size.x {
+                            if font.is_lit(glyph, glyph_x, glyph_y) {
+                                self.put(left + glyph_x as i32, top + glyph_y as i32, color);
+                            }
+                        }
+                    }
+                }
+                left += advance;
+            }
+            top += font.line_height() as i32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_hex("#fff"), Some([255, 255, 255, 255]));
+        assert_eq!(parse_hex("f008"), Some([255, 0, 0, 136]));
+        assert_eq!(parse_hex("#1a2B3c"), Some([26, 43, 60, 255]));
+        assert_eq!(parse_hex("1a2b3c80"), Some([26, 43, 60, 128]));
+        assert_eq!(parse_hex("#12345"), None);
+        assert_eq!(parse_hex("#ggg"), None);
+        assert_eq!(parse_hex(""), None);
+    }
+
+    #[test]
+    fn hsb_fades_from_grey_keep_the_hue() {
+        let blue = Color::from_rgb(0, 0, 255);
+        for grey in ["#000", "#fff", "#808080"] {
+            let grey = Color::from_hex(grey).unwrap();
+            for t in [0.25, 0.5, 0.75] {
+                let [red, green, _] = mix(grey, blue, t, ColorSpace::Hsb).rgb();
+                assert_eq!(red, green, "{:?} to blue at {}", grey, t);
+                let [red, green, _] = mix(blue, grey, t, ColorSpace::Hsb).rgb();
+                assert_eq!(red, green, "blue to {:?} at {}", grey, t);
+            }
+        }
+    }
+
+    #[test]
+    fn hsb_fades_between_greys_keep_their_hues() {
+        let from = Color::from_hsb([350., 0., 0.5], 255);
+        let to = Color::from_hsb([30., 0., 0.5], 255);
+        let middle = mix(from, to, 0.5, ColorSpace::Hsb);
+        assert!((middle.hsb()[0] - 10.).abs() < 1e-9);
+        let [red, green, blue] = middle.rgb();
+        assert!(red == green && green == blue);
+        // greys from rgb have hue 0
+        let black = Color::from_rgb(0, 0, 0);
+        let white = Color::from_rgb(255, 255, 255);
+        let middle = mix(black, white, 0.5, ColorSpace::Hsb);
+        assert_eq!(middle.hsb()[0], 0.);
+        assert_eq!(middle.rgb(), [128, 128, 128]);
+    }
+
+    #[test]
+    fn include_grows_to_the_bounding_box() {
+        let mut area = None;
+        include(&mut area, Rectangle::new(2, 3, 0, 5));
+        assert!(area.is_none());
+        include(&mut area, Rectangle::new(2, 3, 1, 1));
+        include(&mut area, Rectangle::new(5, 1, 2, 1));
+        let area = area.unwrap();
+        assert_eq!(
+            (area.position.x, area.position.y, area.size.x, area.size.y),
+            (2, 1, 5, 3)
+        );
+    }
+
+    #[test]
+    fn update_copies_the_dirty_rows_upside_down() {
+        let (width, height) = (4, 3);
+        let mut data = Data::new(width, height);
+        let mut pixels = vec![0; (width * height * 4) as usize];
+        let all = data.update(&mut pixels).unwrap();
+        assert_eq!((all.size.x, all.size.y), (4, 3));
+        assert!(data.update(&mut pixels).is_none());
+
+        let mut pixels = vec![0; (width * height * 4) as usize];
+        data.put(1, 0, Color::from_rgb(255, 0, 0));
+        data.put(2, 0, Color::from_rgb(0, 255, 0));
+        let dirty = data.update(&mut pixels).unwrap();
+        assert_eq!(
+            (
+                dirty.position.x,
+                dirty.position.y,
+                dirty.size.x,
+                dirty.size.y
+            ),
+            (1, 0, 2, 1)
+        );
+        // row 0 of the image is the last row of the pixels
+        let row = (height - 1) as usize * width as usize * 4;
+        assert_eq!(
+            &pixels[row + 4..row + 12],
+            &[255, 0, 0, 255, 0, 255, 0, 255]
+        );
+        let untouched = pixels
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !(row + 4..row + 12).contains(index))
+            .all(|(_, value)| *value == 0);
+        assert!(untouched);
+    }
+
+    #[test]
+    fn degenerate_polygons_draw_nothing() {
+        let mut data = Data::new(8, 8);
+        let red = Color::from_rgb(255, 0, 0);
+        let point = Vector2::new(3, 3);
+        data.fill_polygon(&[], red);
+        data.fill_polygon(&[point], red);
+        data.fill_polygon(&[point, point, point], red);
+        data.fill_polygon(&[point, Vector2::new(5, 5), point], red);
+        assert_eq!(data.get(3, 3), [255, 255, 255, 255]);
+        let triangle = [Vector2::new(0, 0), Vector2::new(7, 0), Vector2::new(0, 7)];
+        data.fill_polygon(&triangle, red);
+        assert_eq!(data.get(1, 1), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn blend_modes_follow_their_formulas() {
+        let blended = |mode, bottom: [u8; 4], top: [u8; 4]| {
+            let mut bottom = Rgba(bottom);
+            blend(mode, &mut bottom, Rgba(top));
+            bottom.0
+        };
+        let bottom = [200, 100, 0, 255];
+        let normal = blended(BlendMode::Normal, bottom, [100, 200, 255, 255]);
+        assert_eq!(normal, [100, 200, 255, 255]);
+        let half = blended(BlendMode::Normal, bottom, [0, 0, 255, 128]);
+        assert_eq!(half, [100, 50, 128, 255]);
+        let additive = blended(BlendMode::Additive, bottom, [50, 200, 20, 255]);
+        assert_eq!(additive, [250, 255, 20, 255]);
+        let multiply = blended(BlendMode::Multiply, bottom, [128, 255, 0, 255]);
+        assert_eq!(multiply, [100, 100, 0, 255]);
+        let screen = blended(BlendMode::Screen, bottom, [128, 0, 255, 255]);
+        assert_eq!(screen, [228, 100, 255, 255]);
+        let replace = blended(BlendMode::Replace, bottom, [1, 2, 3, 4]);
+        assert_eq!(replace, [1, 2, 3, 4]);
+        // nothing below, so the mode has nothing to mix with
+        let empty = blended(BlendMode::Multiply, [0, 0, 0, 0], [10, 20, 30, 128]);
+        assert_eq!(empty, [10, 20, 30, 128]);
+    }
+
+    #[test]
+    fn put_blends_unless_replacing() {
+        let mut data = Data::new(2, 1);
+        let shadow = Color::from_rgba(0, 0, 0, 128);
+        data.put(0, 0, shadow);
+        assert_eq!(data.get(0, 0), [127, 127, 127, 255]);
+        data.set_blend_mode(BlendMode::Replace);
+        data.put(1, 0, shadow);
+        assert_eq!(data.get(1, 0), [0, 0, 0, 128]);
+    }
+
+    #[test]
+    fn sprites_are_rotated_then_flipped() {
+        let mut sprite = Sprite::new(3, 2);
+        for y in 0..2 {
+            for x in 0..3 {
+                sprite.put(x, y, Color::from_rgb(x as u8 * 100, y as u8 * 100, 0));
+            }
+        }
+        let mut turned: Vec<Vec<[u8; 4]>> = (0..2)
+            .map(|y| (0..3).map(|x| sprite.get(x, y)).collect())
+            .collect();
+        let rotations = [
+            Rotation::None,
+            Rotation::Clockwise90,
+            Rotation::Half,
+            Rotation::Counterclockwise90,
+        ];
+        for rotation in rotations {
+            for (flip_x, flip_y) in [(false, false), (true, false), (false, true), (true, true)] {
+                let mut expected = turned.clone();
+                if flip_x {
+                    for row in &mut expected {
+                        row.reverse();
+                    }
+                }
+                if flip_y {
+                    expected.reverse();
+                }
+                let options = DrawOptions {
+                    flip_x,
+                    flip_y,
+                    rotation,
+                    ..DrawOptions::default()
+                };
+                let mut data = Data::new(3, 3);
+                data.put_sprite_with(&sprite, 0, 0, &options);
+                for (y, row) in expected.iter().enumerate() {
+                    for (x, pixel) in row.iter().enumerate() {
+                        let drawn = data.get(x as u32, y as u32);
+                        assert_eq!(drawn, *pixel, "{:?} {} {}", rotation, flip_x, flip_y);
                     }
                 }
-                Some(vector)
             }
-            None => None,
+            // a quarter turn clockwise: each column read bottom up becomes a row
+            let height = turned.len();
+            let width = turned[0].len();
+            turned = (0..width)
+                .map(|x| (0..height).rev().map(|y| turned[y][x]).collect())
+                .collect();
         }
     }
-    pub fn put_sprite(&mut self, sprite: &mut Sprite, x: u32, y: u32) {
-        imageops::overlay(&mut self.buffer, &sprite.internals.buffer, x, y)
+
+    #[test]
+    fn sprites_are_clipped_at_any_position() {
+        let red = Color::from_rgb(255, 0, 0);
+        let mut sprite = Sprite::new(2, 2);
+        sprite.fill_rectangle(0, 0, 2, 2, red);
+        let mut data = Data::new(4, 4);
+        data.dirty.take();
+        data.put_sprite(&sprite, -1, -1);
+        assert_eq!(data.get(0, 0), [255, 0, 0, 255]);
+        assert_eq!(data.get(1, 0), [255, 255, 255, 255]);
+        assert_eq!(data.get(0, 1), [255, 255, 255, 255]);
+        let dirty = data.dirty.take().unwrap();
+        assert_eq!(
+            (
+                dirty.position.x,
+                dirty.position.y,
+                dirty.size.x,
+                dirty.size.y
+            ),
+            (0, 0, 1, 1)
+        );
+        for (x, y) in [(i32::MIN, 0), (0, i32::MIN), (i32::MAX, 0), (0, i32::MAX)] {
+            data.put_sprite(&sprite, x, y);
+            data.put_sprite_with(&sprite, x, y, &DrawOptions::default());
+            data.put(x, y, red);
+        }
+        assert!(data.dirty.take().is_none());
     }
-    pub fn put(&mut self, x: u32, y: u32, color: Color) {
-        self.buffer.put_pixel(x, y, Rgba([color.rgb[0], color.rgb[1], color.rgb[2], color.alpha]))
+
+    #[test]
+    fn clips_limit_drawing() {
+        let red = Color::from_rgb(255, 0, 0);
+        let mut data = Data::new(4, 4);
+        data.set_clip(Some(Rectangle::new(1, 1, 2, i32::MAX)));
+        let sprite = Sprite::new(4, 4);
+        data.set_blend_mode(BlendMode::Replace);
+        for y in 0..4 {
+            for x in 0..4 {
+                data.put(x, y, red);
+            }
+        }
+        assert_eq!(data.get(0, 1), [255, 255, 255, 255]);
+        assert_eq!(data.get(1, 0), [255, 255, 255, 255]);
+        assert_eq!(data.get(1, 3), [255, 0, 0, 255]);
+        assert_eq!(data.get(3, 3), [255, 255, 255, 255]);
+        data.put_sprite(&sprite, -2, -2);
+        assert_eq!(data.get(1, 1), [255, 255, 255, 255]);
+        assert_eq!(data.get(2, 2), [255, 0, 0, 255]);
+        data.set_clip(Some(Rectangle::new(i32::MAX, i32::MAX, i32::MAX, i32::MAX)));
+        data.put(0, 0, Color::from_rgb(0, 0, 0));
+        assert_eq!(data.get(0, 0), [255, 255, 255, 255]);
     }
 }
@@ -1,12 +1,78 @@
+pub mod camera;
+pub mod font;
 mod internals;
+pub mod palette;
+pub mod recorder;
+pub mod sheet;
+pub mod tiles;
 
+use crate::pack::{EntryKind, Pack};
+use crate::{Rectangle, Vector2};
+use font::Font;
 use internals::Data;
+use sheet::SpriteSheet;
 use std::collections::hash_map::HashMap;
-use std::path::PathBuf;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug)]
+pub enum Error {
+    Open(PathBuf, std::io::Error),
+    Decode(PathBuf, image::ImageError),
+    Pack(PathBuf, std::io::Error),
+    DecodeEntry(PathBuf, String, image::ImageError),
+    UnknownSprite(String),
+    Create(PathBuf, std::io::Error),
+    Encode(PathBuf, image::ImageError),
+    Hex(String),
+    Palette(PathBuf, usize, String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Open(path, error) => write!(f, "open at {:?}: {}", path.as_os_str(), error),
+            Error::Decode(path, error) => write!(f, "decode at {:?}: {}", path.as_os_str(), error),
+            Error::Pack(path, error) => write!(f, "pack at {:?}: {}", path.as_os_str(), error),
+            Error::DecodeEntry(path, name, error) => {
+                write!(f, "decode {:?} in {:?}: {}", name, path.as_os_str(), error)
+            }
+            Error::UnknownSprite(name) => write!(f, "unknown sprite {:?}", name),
+            Error::Create(path, error) => write!(f, "create at {:?}: {}", path.as_os_str(), error),
+            Error::Encode(path, error) => write!(f, "encode at {:?}: {}", path.as_os_str(), error),
+            Error::Hex(hex) => write!(f, "invalid hex color {:?}", hex),
+            Error::Palette(path, line, content) => write!(
+                f,
+                "palette at {:?}, line {}: can not read {:?}",
+                path.as_os_str(),
+                line,
+                content
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Open(_, error) => Some(error),
+            Error::Decode(_, error) => Some(error),
+            Error::Pack(_, error) => Some(error),
+            Error::DecodeEntry(_, _, error) => Some(error),
+            Error::UnknownSprite(_) => None,
+            Error::Create(_, error) => Some(error),
+            Error::Encode(_, error) => Some(error),
+            Error::Hex(_) => None,
+            Error::Palette(_, _, _) => None,
+        }
+    }
+}
 
 #[derive(Copy, Clone)]
 pub struct Color {
-    _hsb: [f64; 3],
+    hsb: [f64; 3],
     rgb: [u8; 3],
     alpha: u8,
 }
@@ -14,24 +80,196 @@ pub struct Color {
 impl Color {
     pub fn from_hsb(hsb: [f64; 3], alpha: u8) -> Color {
         let rgb = internals::hsb_to_rgb(hsb);
-        Color { _hsb: hsb, rgb, alpha }
+        // the hue is kept, so greys built from hsb remember it, see `ColorSpace::Hsb`
+        let hsb = [hsb[0].rem_euclid(360.), hsb[1], hsb[2]];
+        Color { hsb, rgb, alpha }
+    }
+    pub fn from_rgb(red: u8, green: u8, blue: u8) -> Color {
+        Color::from_rgba(red, green, blue, 255)
+    }
+    pub fn from_rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Color {
+        let rgb = [red, green, blue];
+        let hsb = internals::rgb_to_hsb(rgb);
+        Color { hsb, rgb, alpha }
+    }
+    pub fn from_hex(hex: &str) -> Result<Color, Error> {
+        match internals::parse_hex(hex) {
+            Some([red, green, blue, alpha]) => Ok(Color::from_rgba(red, green, blue, alpha)),
+            None => Err(Error::Hex(hex.to_string())),
+        }
+    }
+    // linear light rgb, every channel from 0 to 1
+    pub fn from_linear(linear: [f64; 3], alpha: u8) -> Color {
+        let [red, green, blue] = internals::linear_to_rgb(linear);
+        Color::from_rgba(red, green, blue, alpha)
+    }
+    pub fn from_hsl(hsl: [f64; 3], alpha: u8) -> Color {
+        let [red, green, blue] = internals::hsl_to_rgb(hsl);
+        Color::from_rgba(red, green, blue, alpha)
+    }
+    // CIE L*a*b* with a D65 white point, lightness from 0 to 100
+    pub fn from_lab(lab: [f64; 3], alpha: u8) -> Color {
+        let [red, green, blue] = internals::lab_to_rgb(lab);
+        Color::from_rgba(red, green, blue, alpha)
+    }
+    pub fn with_alpha(self, alpha: u8) -> Color {
+        Color { alpha, ..self }
+    }
+    pub fn rgb(&self) -> [u8; 3] {
+        self.rgb
+    }
+    pub fn rgba(&self) -> [u8; 4] {
+        [self.rgb[0], self.rgb[1], self.rgb[2], self.alpha]
+    }
+    pub fn red(&self) -> u8 {
+        self.rgb[0]
+    }
+    pub fn green(&self) -> u8 {
+        self.rgb[1]
+    }
+    pub fn blue(&self) -> u8 {
+        self.rgb[2]
+    }
+    pub fn alpha(&self) -> u8 {
+        self.alpha
+    }
+    pub fn hsb(&self) -> [f64; 3] {
+        self.hsb
+    }
+    pub fn linear(&self) -> [f64; 3] {
+        internals::rgb_to_linear(self.rgb)
+    }
+    pub fn hsl(&self) -> [f64; 3] {
+        internals::rgb_to_hsl(self.rgb)
+    }
+    pub fn lab(&self) -> [f64; 3] {
+        internals::rgb_to_lab(self.rgb)
+    }
+    // `t` is not clamped, outside 0..1 the color is extrapolated
+    pub fn lerp(self, to: Color, t: f64, space: ColorSpace) -> Color {
+        internals::mix(self, to, t, space)
+    }
+    // `amount` of `other`, from 0 to 1
+    pub fn mix(self, other: Color, amount: f64, space: ColorSpace) -> Color {
+        internals::mix(self, other, amount.clamp(0., 1.), space)
+    }
+    // HSL lightness and saturation, `amount` from 0 to 1 is added and clamped
+    pub fn lighten(self, amount: f64) -> Color {
+        let [hue, saturation, lightness] = self.hsl();
+        let lightness = (lightness + amount).clamp(0., 1.);
+        Color::from_hsl([hue, saturation, lightness], self.alpha)
+    }
+    pub fn darken(self, amount: f64) -> Color {
+        self.lighten(-amount)
+    }
+    // a negative `amount` desaturates
+    pub fn saturate(self, amount: f64) -> Color {
+        let [hue, saturation, lightness] = self.hsl();
+        let saturation = (saturation + amount).clamp(0., 1.);
+        Color::from_hsl([hue, saturation, lightness], self.alpha)
+    }
+    pub fn to_hex(&self) -> String {
+        match self.alpha {
+            255 => format!("#{:02x}{:02x}{:02x}", self.rgb[0], self.rgb[1], self.rgb[2]),
+            alpha => format!(
+                "#{:02x}{:02x}{:02x}{:02x}",
+                self.rgb[0], self.rgb[1], self.rgb[2], alpha
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Color({})", self.to_hex())
+    }
+}
+
+// where colors are interpolated
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+    Rgb,
+    Linear,
+    // The shortest way around the hue circle. A grey, black or white end has no
+    // hue to show, so it takes the other end's; between two greys their own
+    // hues, as given to `Color::from_hsb`, are interpolated.
+    Hsb,
+    Lab,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+    Additive,
+    Multiply,
+    Screen,
+    Replace,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Half,
+    Counterclockwise90,
+}
+
+// The sprite is rotated before it is flipped, so `flip_x` always mirrors it
+// left to right as drawn, whatever the rotation.
+#[derive(Copy, Clone)]
+pub struct DrawOptions {
+    pub region: Option<Rectangle<u32>>,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub rotation: Rotation,
+    pub scale: Vector2<f64>,
+    pub tint: Color,
+    pub opacity: f64,
+}
+
+impl Default for DrawOptions {
+    fn default() -> DrawOptions {
+        DrawOptions {
+            region: None,
+            flip_x: false,
+            flip_y: false,
+            rotation: Rotation::None,
+            scale: Vector2::new(1.0, 1.0),
+            tint: Color::from_hsb([0., 0., 1.], 255),
+            opacity: 1.0,
+        }
     }
 }
 
 pub struct Graphics {
     sprite: Sprite,
     color: Color,
+    font: Font,
 }
 
 impl Graphics {
     pub fn new(width: u32, height: u32) -> Graphics {
         let sprite = Sprite::new(width, height);
         let color = Color::from_hsb([0., 0., 0.], 255);
-        Graphics { sprite, color }
+        let font = Font::new();
+        Graphics {
+            sprite,
+            color,
+            font,
+        }
     }
     pub fn set_color(&mut self, color: Color) {
         self.color = color;
     }
+    pub fn color(&self) -> Color {
+        self.color
+    }
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.sprite.set_blend_mode(mode);
+    }
+    pub fn blend_mode(&self) -> BlendMode {
+        self.sprite.blend_mode()
+    }
     pub fn width(&self) -> u32 {
         self.sprite.width()
     }
@@ -41,37 +279,155 @@ impl Graphics {
     pub fn pixels(&mut self) -> Option<Vec<u8>> {
         self.sprite.pixels()
     }
+    pub fn pixels_mut(&mut self) -> PixelsMut<'_> {
+        self.sprite.pixels_mut()
+    }
+    pub(crate) fn take_upload(&mut self) -> (Option<Vec<u8>>, Option<Rectangle<i32>>) {
+        self.sprite.take_upload()
+    }
+    pub(crate) fn take_convert_time(&mut self) -> Duration {
+        self.sprite.take_convert_time()
+    }
+    // The next upload sends `region` again, whether it changed or not.
+    pub(crate) fn mark_changed(&mut self, region: Rectangle<i32>) {
+        internals::include(&mut self.sprite.changed, region);
+    }
     pub fn return_pixels(&mut self, pixels: Option<Vec<u8>>) {
         self.sprite.return_pixels(pixels);
     }
+    pub fn sprite(&self) -> &Sprite {
+        &self.sprite
+    }
+    pub fn save(&self, path: PathBuf) -> Result<(), Error> {
+        self.sprite.save(path)
+    }
     pub fn put_sprite(&mut self, sprite: &mut Sprite, x: u32, y: u32) {
         self.sprite.put_sprite(sprite, x, y);
     }
+    pub fn put_sprite_region(&mut self, sprite: &Sprite, region: Rectangle<u32>, x: u32, y: u32) {
+        self.sprite.put_sprite_region(sprite, region, x, y);
+    }
+    pub fn put_frame(&mut self, sheet: &SpriteSheet, frame: usize, x: u32, y: u32) {
+        self.sprite.put_frame(sheet, frame, x, y);
+    }
+    pub fn put_sprite_with(&mut self, sprite: &Sprite, x: i32, y: i32, options: &DrawOptions) {
+        self.sprite.put_sprite_with(sprite, x, y, options);
+    }
+    // Blended like any other drawing, so under Normal a translucent color
+    // mixes with the pixel; BlendMode::Replace writes it as is.
     pub fn put(&mut self, x: u32, y: u32) {
         self.sprite.put(x, y, self.color);
     }
+    pub fn set_clip(&mut self, clip: Option<Rectangle<i32>>) {
+        self.sprite.set_clip(clip);
+    }
+    pub fn clip(&self) -> Option<Rectangle<i32>> {
+        self.sprite.clip()
+    }
+    pub fn draw_pixel(&mut self, x: i32, y: i32) {
+        self.sprite.draw_pixel(x, y, self.color);
+    }
+    pub fn draw_sprite(&mut self, sprite: &Sprite, x: i32, y: i32) {
+        self.sprite.draw_sprite(sprite, x, y);
+    }
+    pub fn draw_sprite_region(&mut self, sprite: &Sprite, region: Rectangle<u32>, x: i32, y: i32) {
+        self.sprite.draw_sprite_region(sprite, region, x, y);
+    }
+    pub fn draw_frame(&mut self, sheet: &SpriteSheet, frame: usize, x: i32, y: i32) {
+        self.sprite.draw_frame(sheet, frame, x, y);
+    }
     pub fn apply(&mut self) {
         self.sprite.update();
     }
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        self.sprite.draw_line(x0, y0, x1, y1, self.color);
+    }
+    pub fn draw_rectangle(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        self.sprite.draw_rectangle(x, y, width, height, self.color);
+    }
+    pub fn fill_rectangle(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        self.sprite.fill_rectangle(x, y, width, height, self.color);
+    }
+    pub fn draw_circle(&mut self, x: i32, y: i32, radius: i32) {
+        self.sprite.draw_circle(x, y, radius, self.color);
+    }
+    pub fn fill_circle(&mut self, x: i32, y: i32, radius: i32) {
+        self.sprite.fill_circle(x, y, radius, self.color);
+    }
+    pub fn draw_ellipse(&mut self, x: i32, y: i32, radius_x: i32, radius_y: i32) {
+        self.sprite
+            .draw_ellipse(x, y, radius_x, radius_y, self.color);
+    }
+    pub fn fill_ellipse(&mut self, x: i32, y: i32, radius_x: i32, radius_y: i32) {
+        self.sprite
+            .fill_ellipse(x, y, radius_x, radius_y, self.color);
+    }
+    pub fn draw_polygon(&mut self, vertices: &[Vector2<i32>]) {
+        self.sprite.draw_polygon(vertices, self.color);
+    }
+    pub fn fill_polygon(&mut self, vertices: &[Vector2<i32>]) {
+        self.sprite.fill_polygon(vertices, self.color);
+    }
+    pub fn flood_fill(&mut self, x: u32, y: u32) {
+        self.sprite.flood_fill(x, y, self.color);
+    }
+    pub fn set_font(&mut self, font: Font) {
+        self.font = font;
+    }
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+    pub fn text_width(&self, text: &str) -> u32 {
+        self.font.text_width(text)
+    }
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str) {
+        self.sprite.draw_text(x, y, text, &self.font, self.color);
+    }
 }
 
+#[derive(Clone)]
 pub struct Sprite {
     internals: Data,
     pixels: Option<Vec<u8>>,
+    // the part of `pixels` that changed since it was last uploaded
+    changed: Option<Rectangle<i32>>,
+    // spent in `update` since it was last taken
+    convert_time: Duration,
 }
 
 impl Sprite {
     pub fn new(width: u32, height: u32) -> Sprite {
-        let internals = Data::new(width, height);
-        let pixels = Sprite::create_pixels(width, height);
-        let mut sprite = Sprite { internals, pixels };
+        let mut sprite = Sprite::with_internals(Data::new(width, height));
         sprite.update();
         sprite
     }
-    pub fn load(path: PathBuf) -> Sprite {
-        let internals = Data::load(path);
+    fn with_internals(internals: Data) -> Sprite {
         let pixels = Sprite::create_pixels(internals.width(), internals.height());
-        Sprite { internals, pixels }
+        Sprite {
+            internals,
+            pixels,
+            changed: None,
+            convert_time: Duration::ZERO,
+        }
+    }
+    pub fn load(path: PathBuf) -> Sprite {
+        match Sprite::try_load(path) {
+            Ok(sprite) => sprite,
+            Err(error) => panic!("{}", error),
+        }
+    }
+    pub fn try_load(path: PathBuf) -> Result<Sprite, Error> {
+        Ok(Sprite::with_internals(Data::try_load(path)?))
+    }
+    pub fn from_memory(bytes: &[u8]) -> Result<Sprite, image::ImageError> {
+        Ok(Sprite::with_internals(Data::from_memory(bytes)?))
+    }
+    // the format follows the extension of the path
+    pub fn save(&self, path: PathBuf) -> Result<(), Error> {
+        self.internals.save(path, 1)
+    }
+    pub fn save_scaled(&self, path: PathBuf, scale: u32) -> Result<(), Error> {
+        self.internals.save(path, scale)
     }
     fn create_pixels(width: u32, height: u32) -> Option<Vec<u8>> {
         let mut vector = Vec::with_capacity(width as usize * height as usize * 4);
@@ -91,41 +447,377 @@ impl Sprite {
     pub fn height(&self) -> u32 {
         self.internals.height()
     }
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.internals.set_blend_mode(mode);
+    }
+    pub fn blend_mode(&self) -> BlendMode {
+        self.internals.blend_mode()
+    }
+    // Only the pixels drawn since the last update are converted.
     pub fn update(&mut self) {
-        let pixels = self.pixels();
-        let returned = self.internals.update(pixels);
-        self.return_pixels(returned);
+        let start = Instant::now();
+        if let Some(pixels) = &mut self.pixels {
+            if let Some(region) = self.internals.update(pixels) {
+                internals::include(&mut self.changed, region);
+            }
+        }
+        self.convert_time += start.elapsed();
+    }
+    pub(crate) fn take_convert_time(&mut self) -> Duration {
+        std::mem::take(&mut self.convert_time)
     }
     pub fn pixels(&mut self) -> Option<Vec<u8>> {
+        // whoever takes them may write anywhere
+        let area = Rectangle::new(0, 0, self.width() as i32, self.height() as i32);
+        internals::include(&mut self.changed, area);
         self.pixels.take()
     }
+    // The pixels with the region to upload, none when nothing changed since the last time.
+    pub(crate) fn take_upload(&mut self) -> (Option<Vec<u8>>, Option<Rectangle<i32>>) {
+        (self.pixels.take(), self.changed.take())
+    }
     pub fn return_pixels(&mut self, pixels: Option<Vec<u8>>) {
         self.pixels = pixels;
     }
+    pub fn set_clip(&mut self, clip: Option<Rectangle<i32>>) {
+        self.internals.set_clip(clip);
+    }
+    pub fn clip(&self) -> Option<Rectangle<i32>> {
+        self.internals.clip()
+    }
+    // The u32 calls go through the i32 ones with `as i32`, so coordinates past
+    // i32::MAX wrap around to negative ones and end up clipped away.
     pub fn put_sprite(&mut self, sprite: &mut Sprite, x: u32, y: u32) {
+        self.internals.put_sprite(sprite, x as i32, y as i32);
+    }
+    pub fn put_sprite_region(&mut self, sprite: &Sprite, region: Rectangle<u32>, x: u32, y: u32) {
+        self.internals
+            .put_sprite_region(sprite, region, x as i32, y as i32);
+    }
+    // A frame the sheet doesn't have draws nothing.
+    pub fn put_frame(&mut self, sheet: &SpriteSheet, frame: usize, x: u32, y: u32) {
+        if let Some(region) = sheet.region(frame) {
+            self.put_sprite_region(sheet.sprite(), region, x, y);
+        }
+    }
+    pub fn draw_sprite(&mut self, sprite: &Sprite, x: i32, y: i32) {
         self.internals.put_sprite(sprite, x, y);
     }
+    pub fn draw_sprite_region(&mut self, sprite: &Sprite, region: Rectangle<u32>, x: i32, y: i32) {
+        self.internals.put_sprite_region(sprite, region, x, y);
+    }
+    pub fn draw_frame(&mut self, sheet: &SpriteSheet, frame: usize, x: i32, y: i32) {
+        if let Some(region) = sheet.region(frame) {
+            self.draw_sprite_region(sheet.sprite(), region, x, y);
+        }
+    }
+    pub fn put_sprite_with(&mut self, sprite: &Sprite, x: i32, y: i32, options: &DrawOptions) {
+        self.internals.put_sprite_with(sprite, x, y, options);
+    }
+    // Blended like any other drawing, so under Normal a translucent color
+    // mixes with the pixel; BlendMode::Replace writes it as is.
     pub fn put(&mut self, x: u32, y: u32, color: Color) {
+        self.internals.put(x as i32, y as i32, color);
+    }
+    pub fn draw_pixel(&mut self, x: i32, y: i32, color: Color) {
         self.internals.put(x, y, color);
     }
+    pub fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        self.internals.get(x, y)
+    }
+    pub fn pixels_mut(&mut self) -> PixelsMut<'_> {
+        PixelsMut {
+            buffer: self.internals.buffer_mut(),
+        }
+    }
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        self.internals.draw_line(x0, y0, x1, y1, color);
+    }
+    pub fn draw_rectangle(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        self.internals.draw_rectangle(x, y, width, height, color);
+    }
+    pub fn fill_rectangle(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        self.internals.fill_rectangle(x, y, width, height, color);
+    }
+    pub fn draw_circle(&mut self, x: i32, y: i32, radius: i32, color: Color) {
+        self.internals.draw_ellipse(x, y, radius, radius, color);
+    }
+    pub fn fill_circle(&mut self, x: i32, y: i32, radius: i32, color: Color) {
+        self.internals.fill_ellipse(x, y, radius, radius, color);
+    }
+    pub fn draw_ellipse(&mut self, x: i32, y: i32, radius_x: i32, radius_y: i32, color: Color) {
+        self.internals.draw_ellipse(x, y, radius_x, radius_y, color);
+    }
+    pub fn fill_ellipse(&mut self, x: i32, y: i32, radius_x: i32, radius_y: i32, color: Color) {
+        self.internals.fill_ellipse(x, y, radius_x, radius_y, color);
+    }
+    pub fn draw_polygon(&mut self, vertices: &[Vector2<i32>], color: Color) {
+        self.internals.draw_polygon(vertices, color);
+    }
+    pub fn fill_polygon(&mut self, vertices: &[Vector2<i32>], color: Color) {
+        self.internals.fill_polygon(vertices, color);
+    }
+    pub fn flood_fill(&mut self, x: u32, y: u32, color: Color) {
+        self.internals.flood_fill(x as i32, y as i32, color);
+    }
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, font: &Font, color: Color) {
+        self.internals.draw_text(x, y, text, font, color);
+    }
+}
+
+struct Source {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl Source {
+    fn new(path: PathBuf) -> Source {
+        let modified = Source::modified_at(&path);
+        Source { path, modified }
+    }
+    fn modified_at(path: &Path) -> Option<SystemTime> {
+        match fs::metadata(path) {
+            Ok(metadata) => metadata.modified().ok(),
+            Err(_) => None,
+        }
+    }
+    // Whether the file changed since the last time this was asked.
+    fn changed(&mut self) -> bool {
+        let modified = Source::modified_at(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+    // Loads the file again into `sprite`, which keeps its blend mode and clip.
+    fn reload(&self, sprite: &mut Sprite) -> Result<(), Error> {
+        let mut reloaded = Sprite::try_load(self.path.clone())?;
+        reloaded.set_blend_mode(sprite.blend_mode());
+        reloaded.set_clip(sprite.clip());
+        *sprite = reloaded;
+        Ok(())
+    }
+}
+
+// The pixels of a sprite, top row first, written as they are: no blending and no clip.
+// `Graphics::apply` still has to be called to show them.
+pub struct PixelsMut<'a> {
+    buffer: &'a mut image::RgbaImage,
+}
+
+impl<'a> PixelsMut<'a> {
+    pub fn width(&self) -> u32 {
+        self.buffer.width()
+    }
+    pub fn height(&self) -> u32 {
+        self.buffer.height()
+    }
+    pub fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        self.buffer.get_pixel(x, y).0
+    }
+    pub fn set(&mut self, x: u32, y: u32, color: Color) {
+        self.set_rgba(x, y, color.rgba());
+    }
+    pub fn set_rgba(&mut self, x: u32, y: u32, rgba: [u8; 4]) {
+        self.buffer.get_pixel_mut(x, y).0 = rgba;
+    }
+    pub fn fill(&mut self, color: Color) {
+        let rgba = color.rgba();
+        for pixel in self.buffer.pixels_mut() {
+            pixel.0 = rgba;
+        }
+    }
+    // rgba bytes, `width() * 4` per row
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.buffer
+    }
 }
 
 pub struct SpritesManager {
     dictionary: HashMap<String, Sprite>,
+    sheets: HashMap<String, SpriteSheet>,
+    sources: HashMap<String, Source>,
+    sheet_sources: HashMap<String, Source>,
+    watch_interval: Option<Duration>,
+    since_poll: Duration,
 }
 
 impl SpritesManager {
     pub fn new() -> SpritesManager {
         let dictionary = HashMap::new();
-        SpritesManager { dictionary }
+        let sheets = HashMap::new();
+        let sources = HashMap::new();
+        SpritesManager {
+            dictionary,
+            sheets,
+            sources,
+            sheet_sources: HashMap::new(),
+            watch_interval: None,
+            since_poll: Duration::ZERO,
+        }
     }
     pub fn put(&mut self, name: &str, path: PathBuf) {
         self.dictionary
-            .insert(name.to_string(), Sprite::load(path));
+            .insert(name.to_string(), Sprite::load(path.clone()));
+        self.sources.insert(name.to_string(), Source::new(path));
     }
     pub fn get(&mut self, name: &str) -> &mut Sprite {
         self.dictionary
             .get_mut(name)
             .expect("SpritesManager::get")
     }
+    pub fn try_put(&mut self, name: &str, path: PathBuf) -> Result<(), Error> {
+        let sprite = Sprite::try_load(path.clone())?;
+        self.dictionary.insert(name.to_string(), sprite);
+        self.sources.insert(name.to_string(), Source::new(path));
+        Ok(())
+    }
+    // Puts every sprite entry of the pack at `path` under its entry name.
+    pub fn load_pack(&mut self, path: PathBuf) -> Result<(), Error> {
+        let pack = match Pack::load(&path) {
+            Ok(pack) => pack,
+            Err(error) => return Err(Error::Pack(path, error)),
+        };
+        for entry in pack.entries() {
+            if entry.kind != EntryKind::Sprite {
+                continue;
+            }
+            match Sprite::from_memory(&entry.data) {
+                Ok(sprite) => {
+                    self.dictionary.insert(entry.name.clone(), sprite);
+                }
+                Err(error) => return Err(Error::DecodeEntry(path, entry.name.clone(), error)),
+            }
+        }
+        Ok(())
+    }
+    pub fn try_get(&mut self, name: &str) -> Result<&mut Sprite, Error> {
+        match self.dictionary.get_mut(name) {
+            Some(sprite) => Ok(sprite),
+            None => Err(Error::UnknownSprite(name.to_string())),
+        }
+    }
+    // The sheet is not reloaded when its file changes unless `watch_sheet` says where it came from.
+    pub fn put_sheet(&mut self, name: &str, sheet: SpriteSheet) {
+        self.sheets.insert(name.to_string(), sheet);
+        self.sheet_sources.remove(name);
+    }
+    // Reloads the image of the sheet `name` from `path` when it changes, keeping its frames.
+    pub fn watch_sheet(&mut self, name: &str, path: PathBuf) {
+        self.sheet_sources
+            .insert(name.to_string(), Source::new(path));
+    }
+    pub fn get_sheet(&mut self, name: &str) -> &mut SpriteSheet {
+        self.sheets
+            .get_mut(name)
+            .expect("SpritesManager::get_sheet")
+    }
+    pub fn try_get_sheet(&mut self, name: &str) -> Result<&mut SpriteSheet, Error> {
+        match self.sheets.get_mut(name) {
+            Some(sheet) => Ok(sheet),
+            None => Err(Error::UnknownSprite(name.to_string())),
+        }
+    }
+    // Opt in to polling the files sprites were loaded from, every `interval`
+    // worth of `update` deltas.
+    pub fn watch(&mut self, interval: Duration) {
+        self.watch_interval = Some(interval);
+        self.since_poll = Duration::ZERO;
+    }
+    pub fn unwatch(&mut self) {
+        self.watch_interval = None;
+    }
+    pub fn update(&mut self, delta: Duration) -> Vec<Error> {
+        let interval = match self.watch_interval {
+            Some(interval) => interval,
+            None => return Vec::new(),
+        };
+        self.since_poll += delta;
+        if self.since_poll < interval {
+            return Vec::new();
+        }
+        self.since_poll = Duration::ZERO;
+        self.reload_changed()
+    }
+    // Reloads every sprite and watched sheet whose file changed since it was loaded.
+    // A sprite that fails to load keeps its previous version until the file changes again.
+    pub fn reload_changed(&mut self) -> Vec<Error> {
+        let mut errors = Vec::new();
+        for (name, source) in &mut self.sources {
+            if !source.changed() {
+                continue;
+            }
+            if let Some(sprite) = self.dictionary.get_mut(name) {
+                if let Err(error) = source.reload(sprite) {
+                    errors.push(error);
+                }
+            }
+        }
+        for (name, source) in &mut self.sheet_sources {
+            if !source.changed() {
+                continue;
+            }
+            if let Some(sheet) = self.sheets.get_mut(name) {
+                if let Err(error) = source.reload(sheet.sprite_mut()) {
+                    errors.push(error);
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn reloads_keep_blend_mode_and_clip() {
+        let path = std::env::temp_dir().join(format!("uninutsh-{}-reload.png", std::process::id()));
+        let blue = Color::from_rgb(0, 0, 128);
+        let red = Color::from_rgb(255, 0, 0);
+        let mut file = Sprite::new(4, 4);
+        file.fill_rectangle(0, 0, 4, 4, blue);
+        file.save(path.clone()).unwrap();
+        let mut sprites = SpritesManager::new();
+        sprites.put("sprite", path.clone());
+        sprites.put_sheet("sheet", SpriteSheet::load(path.clone()));
+        sprites.watch_sheet("sheet", path.clone());
+        let clip = Some(Rectangle::new(1, 1, 2, 2));
+
+        let sprite = sprites.get("sprite");
+        sprite.draw_pixel(2, 2, red);
+        sprite.set_blend_mode(BlendMode::Additive);
+        sprite.set_clip(clip);
+        assert_eq!(sprite.get(2, 2), [255, 0, 0, 255]);
+        let sheet = sprites.get_sheet("sheet").sprite_mut();
+        sheet.draw_pixel(2, 2, red);
+        sheet.set_blend_mode(BlendMode::Additive);
+        sheet.set_clip(clip);
+        assert_eq!(sheet.get(2, 2), [255, 0, 0, 255]);
+
+        // as if the files changed
+        for source in sprites.sources.values_mut() {
+            source.modified = Some(UNIX_EPOCH);
+        }
+        for source in sprites.sheet_sources.values_mut() {
+            source.modified = Some(UNIX_EPOCH);
+        }
+        let errors = sprites.reload_changed();
+        fs::remove_file(&path).unwrap();
+        assert!(errors.is_empty());
+
+        let sprite = sprites.get("sprite");
+        assert_eq!(sprite.get(2, 2), [0, 0, 128, 255]);
+        assert_eq!(sprite.blend_mode(), BlendMode::Additive);
+        let kept = sprite.clip().unwrap();
+        assert_eq!((kept.position.x, kept.size.x), (1, 2));
+        let sheet = sprites.get_sheet("sheet").sprite_mut();
+        assert_eq!(sheet.get(2, 2), [0, 0, 128, 255]);
+        assert_eq!(sheet.blend_mode(), BlendMode::Additive);
+        let kept = sheet.clip().unwrap();
+        assert_eq!((kept.position.x, kept.size.x), (1, 2));
+    }
 }
@@ -0,0 +1,257 @@
+use super::{internals, Color, ColorSpace, Error};
+use std::fs;
+use std::path::PathBuf;
+
+pub struct Palette {
+    name: String,
+    colors: Vec<Color>,
+}
+
+impl Palette {
+    pub fn new(name: &str) -> Palette {
+        Palette::from_colors(name, Vec::new())
+    }
+    pub fn from_colors(name: &str, colors: Vec<Color>) -> Palette {
+        Palette {
+            name: name.to_string(),
+            colors,
+        }
+    }
+    pub fn load(path: PathBuf) -> Palette {
+        match Palette::try_load(path) {
+            Ok(palette) => palette,
+            Err(error) => panic!("{}", error),
+        }
+    }
+    // .gpl is a GIMP palette, .hex one hex color per line, anything else is text
+    // with one hex color or "red green blue" per line and ';' starting a comment.
+    pub fn try_load(path: PathBuf) -> Result<Palette, Error> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) => return Err(Error::Open(path, error)),
+        };
+        let name = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            None => String::new(),
+        };
+        let mut palette = Palette::new(&name);
+        let gpl = match path.extension() {
+            Some(extension) => extension.eq_ignore_ascii_case("gpl"),
+            None => false,
+        };
+        let parsed = if gpl {
+            palette.parse_gpl(&text)
+        } else {
+            palette.parse_text(&text)
+        };
+        match parsed {
+            Ok(()) => Ok(palette),
+            Err((line, content)) => Err(Error::Palette(path, line, content)),
+        }
+    }
+    fn parse_gpl(&mut self, text: &str) -> Result<(), (usize, String)> {
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if index == 0 {
+                if line != "GIMP Palette" {
+                    return Err((1, line.to_string()));
+                }
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("Name:") {
+                self.name = name.trim().to_string();
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') || line.starts_with("Columns:") {
+                continue;
+            }
+            match decimal(line) {
+                Some(color) => self.colors.push(color),
+                None => return Err((index + 1, line.to_string())),
+            }
+        }
+        Ok(())
+    }
+    fn parse_text(&mut self, text: &str) -> Result<(), (usize, String)> {
+        for (index, line) in text.lines().enumerate() {
+            let line = match line.find(';') {
+                Some(comment) => &line[..comment],
+                None => line,
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let color = match Color::from_hex(line) {
+                Ok(color) => Some(color),
+                Err(_) => decimal(line),
+            };
+            match color {
+                Some(color) => self.colors.push(color),
+                None => return Err((index + 1, line.to_string())),
+            }
+        }
+        Ok(())
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn push(&mut self, color: Color) {
+        self.colors.push(color);
+    }
+    pub fn get(&self, index: usize) -> Option<Color> {
+        self.colors.get(index).copied()
+    }
+    // Any index is valid, it wraps around the palette; panics on an empty one.
+    pub fn wrapped(&self, index: usize) -> Color {
+        self.colors[index % self.colors.len()]
+    }
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+}
+
+// "red green blue" with anything after the third number ignored
+fn decimal(line: &str) -> Option<Color> {
+    let mut values = line
+        .split_whitespace()
+        .map(|value| value.parse::<u8>().ok());
+    let red = values.next()??;
+    let green = values.next()??;
+    let blue = values.next()??;
+    Some(Color::from_rgb(red, green, blue))
+}
+
+#[derive(Clone)]
+pub struct Gradient {
+    stops: Vec<(f64, Color)>,
+    space: ColorSpace,
+}
+
+impl Gradient {
+    pub fn new(space: ColorSpace) -> Gradient {
+        Gradient {
+            stops: Vec::new(),
+            space,
+        }
+    }
+    // The colors of `palette` evenly spread from 0 to 1.
+    pub fn from_palette(palette: &Palette, space: ColorSpace) -> Gradient {
+        let mut gradient = Gradient::new(space);
+        let last = palette.len().saturating_sub(1).max(1) as f64;
+        for (index, color) in palette.colors().iter().enumerate() {
+            gradient.add_stop(index as f64 / last, *color);
+        }
+        gradient
+    }
+    pub fn add_stop(&mut self, position: f64, color: Color) {
+        let index = self
+            .stops
+            .iter()
+            .position(|(stop, _)| *stop > position)
+            .unwrap_or(self.stops.len());
+        self.stops.insert(index, (position, color));
+    }
+    pub fn space(&self) -> ColorSpace {
+        self.space
+    }
+    pub fn set_space(&mut self, space: ColorSpace) {
+        self.space = space;
+    }
+    pub fn is_empty(&self) -> bool {
+        self.stops.is_empty()
+    }
+    // Positions before the first stop or after the last one take its color;
+    // None on a gradient without stops.
+    pub fn at(&self, position: f64) -> Option<Color> {
+        let (first, first_color) = *self.stops.first()?;
+        if position <= first {
+            return Some(first_color);
+        }
+        for pair in self.stops.windows(2) {
+            let (from, from_color) = pair[0];
+            let (to, to_color) = pair[1];
+            if position <= to {
+                let t = (position - from) / (to - from);
+                return Some(internals::mix(from_color, to_color, t, self.space));
+            }
+        }
+        self.stops.last().map(|(_, color)| *color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gimp_palettes() {
+        let mut palette = Palette::new("");
+        let text = "GIMP Palette\nName: Dawn\nColumns: 4\n# comment\n255 0 0 red\n  0 128 255\n";
+        assert!(palette.parse_gpl(text).is_ok());
+        assert_eq!(palette.name(), "Dawn");
+        assert_eq!(palette.len(), 2);
+        assert_eq!(palette.colors()[0].rgba(), [255, 0, 0, 255]);
+        assert_eq!(palette.colors()[1].rgba(), [0, 128, 255, 255]);
+
+        let mut palette = Palette::new("");
+        assert_eq!(
+            palette.parse_gpl("255 0 0\n"),
+            Err((1, "255 0 0".to_string()))
+        );
+        let mut palette = Palette::new("");
+        let broken = "GIMP Palette\n255 0\n";
+        assert_eq!(palette.parse_gpl(broken), Err((2, "255 0".to_string())));
+    }
+
+    #[test]
+    fn text_palettes() {
+        let mut palette = Palette::new("");
+        let text = "; hex or decimal\n#ff0000\n\n00ff00 ; green\n0 0 255\n";
+        assert!(palette.parse_text(text).is_ok());
+        let colors: Vec<[u8; 4]> = palette.colors().iter().map(|color| color.rgba()).collect();
+        assert_eq!(
+            colors,
+            vec![[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]]
+        );
+        let mut palette = Palette::new("");
+        assert_eq!(
+            palette.parse_text("#ff0000\nred\n"),
+            Err((2, "red".to_string()))
+        );
+    }
+
+    #[test]
+    fn loading_reports_the_line() {
+        let path = std::env::temp_dir().join(format!("uninutsh-{}-broken.txt", std::process::id()));
+        fs::write(&path, "#000\n#zzz\n").unwrap();
+        let result = Palette::try_load(path.clone());
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(Error::Palette(_, line, content)) => {
+                assert_eq!((line, content.as_str()), (2, "#zzz"))
+            }
+            _ => panic!("expected a palette error"),
+        }
+    }
+
+    #[test]
+    fn gradients_fade_between_stops() {
+        let mut gradient = Gradient::new(ColorSpace::Rgb);
+        assert!(gradient.at(0.5).is_none());
+        gradient.add_stop(1., Color::from_rgb(200, 100, 0));
+        gradient.add_stop(0., Color::from_rgb(0, 0, 0));
+        assert_eq!(gradient.at(-1.).unwrap().rgba(), [0, 0, 0, 255]);
+        assert_eq!(gradient.at(0.5).unwrap().rgba(), [100, 50, 0, 255]);
+        assert_eq!(gradient.at(2.).unwrap().rgba(), [200, 100, 0, 255]);
+        let mut single = Gradient::new(ColorSpace::Lab);
+        single.add_stop(0.3, Color::from_rgb(1, 2, 3));
+        assert_eq!(single.at(0.9).unwrap().rgba(), [1, 2, 3, 255]);
+    }
+}
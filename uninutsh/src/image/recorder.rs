@@ -0,0 +1,156 @@
+use super::{Error, Sprite};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, RgbaImage,
+};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::Duration;
+
+enum Output {
+    Sequence(PathBuf),
+    Gif(PathBuf, GifEncoder<BufWriter<File>>),
+}
+
+// Writes the presented frames of a window, see `Window::start_recording`.
+pub struct Recorder {
+    output: Output,
+    scale: u32,
+    frames: usize,
+    // a gif frame is written once the next one tells how long it was shown
+    pending: Option<(RgbaImage, Duration)>,
+    last_delay: Duration,
+    error: Option<Error>,
+}
+
+impl Recorder {
+    // Numbered png files, 000000.png, 000001.png... inside `directory`.
+    pub fn png_sequence(directory: PathBuf, scale: u32) -> Result<Recorder, Error> {
+        if let Err(error) = fs::create_dir_all(&directory) {
+            return Err(Error::Create(directory, error));
+        }
+        Ok(Recorder::new(Output::Sequence(directory), scale))
+    }
+    // An endlessly looping gif, each frame lasts until the next one was presented.
+    pub fn gif(path: PathBuf, scale: u32) -> Result<Recorder, Error> {
+        let file = match File::create(&path) {
+            Ok(file) => file,
+            Err(error) => return Err(Error::Create(path, error)),
+        };
+        let mut encoder = GifEncoder::new(BufWriter::new(file));
+        if let Err(error) = encoder.set_repeat(Repeat::Infinite) {
+            return Err(Error::Encode(path, error));
+        }
+        Ok(Recorder::new(Output::Gif(path, encoder), scale))
+    }
+    fn new(output: Output, scale: u32) -> Recorder {
+        Recorder {
+            output,
+            scale: scale.max(1),
+            frames: 0,
+            pending: None,
+            last_delay: Duration::ZERO,
+            error: None,
+        }
+    }
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+    // `time` is when the frame was presented, from any fixed starting point.
+    // After the first error the remaining frames are dropped, `finish` reports it.
+    pub fn record(&mut self, sprite: &Sprite, time: Duration) {
+        if self.error.is_some() {
+            return;
+        }
+        let image = sprite.internals.scaled(self.scale);
+        let result = match &mut self.output {
+            Output::Sequence(directory) => {
+                let path = directory.join(format!("{:06}.png", self.frames));
+                match image.save(&path) {
+                    Ok(()) => Ok(()),
+                    Err(error) => Err(Error::Encode(path, error)),
+                }
+            }
+            Output::Gif(_, _) => {
+                let result = match self.pending.take() {
+                    Some((previous, previous_time)) => {
+                        let delay = time.saturating_sub(previous_time);
+                        self.last_delay = delay;
+                        self.encode(previous, delay)
+                    }
+                    None => Ok(()),
+                };
+                self.pending = Some((image, time));
+                result
+            }
+        };
+        match result {
+            Ok(()) => self.frames += 1,
+            Err(error) => self.error = Some(error),
+        }
+    }
+    fn encode(&mut self, image: RgbaImage, delay: Duration) -> Result<(), Error> {
+        if let Output::Gif(path, encoder) = &mut self.output {
+            let frame = Frame::from_parts(image, 0, 0, Delay::from_saturating_duration(delay));
+            if let Err(error) = encoder.encode_frame(frame) {
+                return Err(Error::Encode(path.clone(), error));
+            }
+        }
+        Ok(())
+    }
+    fn flush(&mut self) {
+        if let Some((image, _)) = self.pending.take() {
+            let delay = self.last_delay;
+            if let Err(error) = self.encode(image, delay) {
+                if self.error.is_none() {
+                    self.error = Some(error);
+                }
+            }
+        }
+    }
+    // Writes the last gif frame and returns how many frames were recorded.
+    pub fn finish(mut self) -> Result<usize, Error> {
+        self.flush();
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(self.frames),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{codecs::gif::GifDecoder, AnimationDecoder};
+
+    #[test]
+    fn gif_frames_last_until_the_next_one() {
+        let path = std::env::temp_dir().join(format!("uninutsh-{}-delays.gif", std::process::id()));
+        let mut recorder = Recorder::gif(path.clone(), 2).unwrap();
+        let sprite = Sprite::new(3, 2);
+        for milliseconds in [1000, 1040, 1100] {
+            recorder.record(&sprite, Duration::from_millis(milliseconds));
+        }
+        assert_eq!(recorder.finish().unwrap(), 3);
+        let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        fs::remove_file(&path).unwrap();
+        let delays: Vec<u32> = frames
+            .iter()
+            .map(|frame| {
+                let (numerator, denominator) = frame.delay().numer_denom_ms();
+                numerator / denominator
+            })
+            .collect();
+        // the last frame has no next one and keeps the delay before it
+        assert_eq!(delays, vec![40, 60, 60]);
+        assert_eq!(frames[0].buffer().dimensions(), (6, 4));
+    }
+}
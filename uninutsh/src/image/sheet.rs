@@ -0,0 +1,191 @@
+use super::{Error, Sprite};
+use crate::Rectangle;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub struct SpriteSheet {
+    sprite: Sprite,
+    frames: Vec<Rectangle<u32>>,
+    names: HashMap<String, usize>,
+}
+
+impl SpriteSheet {
+    pub fn new(sprite: Sprite) -> SpriteSheet {
+        SpriteSheet {
+            sprite,
+            frames: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+    pub fn load(path: PathBuf) -> SpriteSheet {
+        SpriteSheet::new(Sprite::load(path))
+    }
+    pub fn try_load(path: PathBuf) -> Result<SpriteSheet, Error> {
+        Ok(SpriteSheet::new(Sprite::try_load(path)?))
+    }
+    // Cuts the whole sprite into `width` x `height` frames, indexed row by row;
+    // panics on a frame size of zero.
+    pub fn from_grid(sprite: Sprite, width: u32, height: u32) -> SpriteSheet {
+        assert!(
+            width > 0 && height > 0,
+            "frame size must not be zero, got {}x{}",
+            width,
+            height
+        );
+        let columns = sprite.width() / width;
+        let rows = sprite.height() / height;
+        let mut sheet = SpriteSheet::new(sprite);
+        for row in 0..rows {
+            for column in 0..columns {
+                let region = Rectangle::new(column * width, row * height, width, height);
+                sheet.frames.push(region);
+            }
+        }
+        sheet
+    }
+    pub fn add_frame(&mut self, name: &str, region: Rectangle<u32>) -> usize {
+        let index = self.frames.len();
+        self.frames.push(region);
+        self.names.insert(name.to_string(), index);
+        index
+    }
+    pub fn name_frame(&mut self, name: &str, index: usize) {
+        self.names.insert(name.to_string(), index);
+    }
+    pub fn frame(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+    pub fn region(&self, index: usize) -> Option<Rectangle<u32>> {
+        self.frames.get(index).copied()
+    }
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+    pub fn sprite(&self) -> &Sprite {
+        &self.sprite
+    }
+    pub fn sprite_mut(&mut self) -> &mut Sprite {
+        &mut self.sprite
+    }
+}
+
+#[derive(Clone)]
+pub struct Animation {
+    frames: Vec<(usize, Duration)>,
+    looping: bool,
+    current: usize,
+    elapsed: Duration,
+}
+
+impl Animation {
+    pub fn new(looping: bool) -> Animation {
+        Animation {
+            frames: Vec::new(),
+            looping,
+            current: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+    pub fn with_frames(frames: &[usize], duration: Duration, looping: bool) -> Animation {
+        let mut animation = Animation::new(looping);
+        for frame in frames {
+            animation.add_frame(*frame, duration);
+        }
+        animation
+    }
+    pub fn add_frame(&mut self, frame: usize, duration: Duration) {
+        self.frames.push((frame, duration));
+    }
+    pub fn update(&mut self, delta: Duration) {
+        if self.frames.is_empty() {
+            return;
+        }
+        self.elapsed += delta;
+        loop {
+            let duration = self.frames[self.current].1;
+            if self.elapsed < duration || duration.is_zero() {
+                return;
+            }
+            if self.current + 1 == self.frames.len() {
+                if !self.looping {
+                    self.elapsed = duration;
+                    return;
+                }
+                self.current = 0;
+            } else {
+                self.current += 1;
+            }
+            self.elapsed -= duration;
+        }
+    }
+    // None while the animation has no frames.
+    pub fn frame(&self) -> Option<usize> {
+        self.frames.get(self.current).map(|(frame, _)| *frame)
+    }
+    pub fn is_finished(&self) -> bool {
+        !self.looping
+            && self.current + 1 == self.frames.len()
+            && self.elapsed >= self.frames[self.current].1
+    }
+    pub fn reset(&mut self) {
+        self.current = 0;
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grids_cut_whole_frames_row_by_row() {
+        let sheet = SpriteSheet::from_grid(Sprite::new(10, 7), 4, 3);
+        assert_eq!(sheet.len(), 4);
+        let region = sheet.region(3).unwrap();
+        assert_eq!(
+            (
+                region.position.x,
+                region.position.y,
+                region.size.x,
+                region.size.y
+            ),
+            (4, 3, 4, 3)
+        );
+        assert!(sheet.region(4).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "frame size must not be zero")]
+    fn zero_frame_size_panics() {
+        SpriteSheet::from_grid(Sprite::new(10, 7), 0, 3);
+    }
+
+    #[test]
+    fn animations_step_through_their_frames() {
+        let step = Duration::from_millis(100);
+        let mut empty = Animation::new(true);
+        empty.update(step);
+        assert_eq!(empty.frame(), None);
+        assert!(!empty.is_finished());
+
+        let mut looping = Animation::with_frames(&[4, 5, 6], step, true);
+        assert_eq!(looping.frame(), Some(4));
+        looping.update(Duration::from_millis(250));
+        assert_eq!(looping.frame(), Some(6));
+        looping.update(step);
+        assert_eq!(looping.frame(), Some(4));
+        assert!(!looping.is_finished());
+
+        let mut once = Animation::with_frames(&[4, 5], step, false);
+        once.update(Duration::from_secs(1));
+        assert_eq!(once.frame(), Some(5));
+        assert!(once.is_finished());
+        once.reset();
+        assert_eq!(once.frame(), Some(4));
+        assert!(!once.is_finished());
+    }
+}
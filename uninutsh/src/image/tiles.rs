@@ -0,0 +1,128 @@
+use super::{camera::Camera, sheet::SpriteSheet, Graphics};
+use crate::Vector2;
+
+pub struct TileMap {
+    size: Vector2<u32>,
+    tile_size: Vector2<u32>,
+    tiles: Vec<Option<usize>>,
+}
+
+impl TileMap {
+    // Panics on a tile size of zero.
+    pub fn new(width: u32, height: u32, tile_size: Vector2<u32>) -> TileMap {
+        assert!(
+            tile_size.x > 0 && tile_size.y > 0,
+            "tile size must not be zero, got {}x{}",
+            tile_size.x,
+            tile_size.y
+        );
+        let tiles = vec![None; width as usize * height as usize];
+        TileMap {
+            size: Vector2::new(width, height),
+            tile_size,
+            tiles,
+        }
+    }
+    pub fn width(&self) -> u32 {
+        self.size.x
+    }
+    pub fn height(&self) -> u32 {
+        self.size.y
+    }
+    pub fn tile_size(&self) -> Vector2<u32> {
+        self.tile_size
+    }
+    pub fn pixel_width(&self) -> u32 {
+        self.size.x * self.tile_size.x
+    }
+    pub fn pixel_height(&self) -> u32 {
+        self.size.y * self.tile_size.y
+    }
+    fn index_at(&self, x: u32, y: u32) -> usize {
+        y as usize * self.size.x as usize + x as usize
+    }
+    // Returns false, leaving the map alone, when (x, y) is outside of it.
+    pub fn set(&mut self, x: u32, y: u32, tile: Option<usize>) -> bool {
+        if x >= self.size.x || y >= self.size.y {
+            return false;
+        }
+        let index = self.index_at(x, y);
+        self.tiles[index] = tile;
+        true
+    }
+    pub fn get(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.size.x || y >= self.size.y {
+            return None;
+        }
+        self.tiles[self.index_at(x, y)]
+    }
+    pub fn tile_at(&self, x: i32, y: i32) -> Option<Vector2<u32>> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let tile_x = x as u32 / self.tile_size.x;
+        let tile_y = y as u32 / self.tile_size.y;
+        if tile_x >= self.size.x || tile_y >= self.size.y {
+            return None;
+        }
+        Some(Vector2::new(tile_x, tile_y))
+    }
+    // Draws the tiles the camera can see, with the map's top left at world (0, 0).
+    pub fn draw(&self, graphics: &mut Graphics, camera: &Camera, sheet: &SpriteSheet) {
+        let view = camera.view(graphics);
+        let tile_width = self.tile_size.x as i32;
+        let tile_height = self.tile_size.y as i32;
+        let first_x = view.position.x.div_euclid(tile_width).max(0);
+        let first_y = view.position.y.div_euclid(tile_height).max(0);
+        let last_x = (view.position.x + view.size.x).div_euclid(tile_width);
+        let last_y = (view.position.y + view.size.y).div_euclid(tile_height);
+        let last_x = last_x.min(self.size.x as i32 - 1);
+        let last_y = last_y.min(self.size.y as i32 - 1);
+        for tile_y in first_y..=last_y {
+            for tile_x in first_x..=last_x {
+                if let Some(tile) = self.get(tile_x as u32, tile_y as u32) {
+                    let x = tile_x * tile_width;
+                    let y = tile_y * tile_height;
+                    camera.draw_frame(graphics, sheet, tile, x, y);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get_stay_inside_the_map() {
+        let mut map = TileMap::new(3, 2, Vector2::new(8, 8));
+        assert!(map.set(2, 1, Some(4)));
+        assert_eq!(map.get(2, 1), Some(4));
+        assert!(!map.set(3, 0, Some(1)));
+        assert!(!map.set(0, 2, Some(1)));
+        assert_eq!(map.get(0, 1), None);
+        assert_eq!(map.get(3, 0), None);
+        assert_eq!(map.tiles.iter().filter(|tile| tile.is_some()).count(), 1);
+        assert!(map.set(2, 1, None));
+        assert_eq!(map.get(2, 1), None);
+    }
+
+    #[test]
+    fn tile_at_maps_pixels_to_tiles() {
+        let map = TileMap::new(3, 2, Vector2::new(8, 4));
+        let tile = map.tile_at(17, 7).unwrap();
+        assert_eq!((tile.x, tile.y), (2, 1));
+        let tile = map.tile_at(0, 0).unwrap();
+        assert_eq!((tile.x, tile.y), (0, 0));
+        assert!(map.tile_at(-1, 0).is_none());
+        assert!(map.tile_at(24, 0).is_none());
+        assert!(map.tile_at(0, 8).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "tile size must not be zero")]
+    fn zero_tile_size_panics() {
+        TileMap::new(3, 2, Vector2::new(0, 8));
+    }
+}
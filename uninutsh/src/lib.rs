@@ -3,8 +3,9 @@ pub mod image;
 pub mod window;
 pub mod audio;
 pub mod nutshell;
+pub mod pack;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct Vector2<T> {
     pub x: T,
     pub y: T,
@@ -16,6 +17,7 @@ impl<T> Vector2<T> {
     }
 }
 
+#[derive(Copy, Clone, Debug)]
 pub struct Rectangle<T> {
     pub position: Vector2<T>,
     pub size: Vector2<T>,
@@ -28,3 +30,12 @@ impl<T> Rectangle<T> {
         Rectangle { position, size }
     }
 }
+
+impl<T: Copy + PartialOrd + std::ops::Add<Output = T>> Rectangle<T> {
+    pub fn contains(&self, x: T, y: T) -> bool {
+        x >= self.position.x
+            && y >= self.position.y
+            && x < self.position.x + self.size.x
+            && y < self.position.y + self.size.y
+    }
+}
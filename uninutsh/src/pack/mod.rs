@@ -0,0 +1,235 @@
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+// A pack is "UNPK", a little endian u32 version and u32 entry count, then per
+// entry a u8 kind, u32 name length, the name, u64 data length and the data.
+const MAGIC: &[u8; 4] = b"UNPK";
+const VERSION: u32 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    Sprite,
+    Sound,
+    Other,
+}
+
+impl EntryKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            EntryKind::Sprite => 0,
+            EntryKind::Sound => 1,
+            EntryKind::Other => 255,
+        }
+    }
+    fn from_byte(byte: u8) -> EntryKind {
+        match byte {
+            0 => EntryKind::Sprite,
+            1 => EntryKind::Sound,
+            _ => EntryKind::Other,
+        }
+    }
+}
+
+pub struct Entry {
+    pub name: String,
+    pub kind: EntryKind,
+    pub data: Vec<u8>,
+}
+
+pub struct Pack {
+    entries: Vec<Entry>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// `file_name` in the directory of the running executable, so binaries find
+// their pack wherever they are started from.
+pub fn beside_executable(file_name: &str) -> Option<PathBuf> {
+    let executable = env::current_exe().ok()?;
+    Some(executable.parent()?.join(file_name))
+}
+
+impl Pack {
+    pub fn new() -> Pack {
+        Pack {
+            entries: Vec::new(),
+        }
+    }
+    pub fn add(&mut self, name: &str, kind: EntryKind, data: Vec<u8>) {
+        self.entries.retain(|entry| entry.name != name);
+        self.entries.push(Entry {
+            name: name.to_string(),
+            kind,
+            data,
+        });
+    }
+    pub fn add_file(&mut self, name: &str, kind: EntryKind, path: &Path) -> io::Result<()> {
+        let data = fs::read(path)?;
+        self.add(name, kind, data);
+        Ok(())
+    }
+    // Adds every file of `directory` with one of `extensions` under its file stem.
+    pub fn add_directory(
+        &mut self,
+        kind: EntryKind,
+        directory: &Path,
+        extensions: &[&str],
+    ) -> io::Result<()> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            let wanted = match extension {
+                Some(extension) => extensions
+                    .iter()
+                    .any(|wanted| wanted.eq_ignore_ascii_case(extension)),
+                None => false,
+            };
+            if path.is_file() && wanted {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in paths {
+            let name = match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().to_string(),
+                None => continue,
+            };
+            self.add_file(&name, kind, &path)?;
+        }
+        Ok(())
+    }
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for entry in &self.entries {
+            writer.write_all(&[entry.kind.to_byte()])?;
+            writer.write_all(&(entry.name.len() as u32).to_le_bytes())?;
+            writer.write_all(entry.name.as_bytes())?;
+            writer.write_all(&(entry.data.len() as u64).to_le_bytes())?;
+            writer.write_all(&entry.data)?;
+        }
+        writer.flush()
+    }
+    pub fn load(path: &Path) -> io::Result<Pack> {
+        let mut reader = io::BufReader::new(fs::File::open(path)?);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a pack file"));
+        }
+        if read_u32(&mut reader)? != VERSION {
+            return Err(invalid("unsupported pack version"));
+        }
+        let count = read_u32(&mut reader)?;
+        let mut pack = Pack::new();
+        for _i in 0..count {
+            let mut kind = [0; 1];
+            reader.read_exact(&mut kind)?;
+            let name_length = read_u32(&mut reader)? as u64;
+            let mut name = Vec::new();
+            (&mut reader).take(name_length).read_to_end(&mut name)?;
+            if name.len() as u64 != name_length {
+                return Err(invalid("truncated entry name"));
+            }
+            let name = match String::from_utf8(name) {
+                Ok(name) => name,
+                Err(_) => return Err(invalid("entry name is not utf-8")),
+            };
+            let data_length = read_u64(&mut reader)?;
+            let mut data = Vec::new();
+            (&mut reader).take(data_length).read_to_end(&mut data)?;
+            if data.len() as u64 != data_length {
+                return Err(invalid("truncated entry data"));
+            }
+            pack.entries.push(Entry {
+                name,
+                kind: EntryKind::from_byte(kind[0]),
+                data,
+            });
+        }
+        Ok(pack)
+    }
+}
+
+impl Default for Pack {
+    fn default() -> Pack {
+        Pack::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("uninutsh-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut pack = Pack::new();
+        pack.add("background", EntryKind::Sprite, vec![1, 2, 3]);
+        pack.add("theme", EntryKind::Sound, Vec::new());
+        pack.add("notes", EntryKind::Other, b"text".to_vec());
+        let path = temporary("round-trip.pack");
+        pack.save(&path).unwrap();
+        let loaded = Pack::load(&path);
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.len(), 3);
+        for (entry, original) in loaded.entries().iter().zip(pack.entries()) {
+            assert_eq!(entry.name, original.name);
+            assert_eq!(entry.kind, original.kind);
+            assert_eq!(entry.data, original.data);
+        }
+        assert_eq!(loaded.get("notes").unwrap().data, b"text");
+    }
+
+    #[test]
+    fn truncated_packs_fail() {
+        let mut pack = Pack::new();
+        pack.add("background", EntryKind::Sprite, vec![7; 16]);
+        let path = temporary("truncated.pack");
+        pack.save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        for length in 0..bytes.len() {
+            fs::write(&path, &bytes[..length]).unwrap();
+            assert!(Pack::load(&path).is_err(), "{} bytes loaded", length);
+        }
+        fs::write(&path, b"PACK\x01\0\0\0\0\0\0\0").unwrap();
+        let error = Pack::load(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,64 @@
+use super::{Frame, Redraw, WindowEvent, WindowOptions};
+use crate::Vector2;
+use std::time::{Duration, Instant};
+
+pub struct Data {
+    update_delta: Duration,
+    redraw: Redraw,
+    size: Vector2<u32>,
+}
+
+impl Data {
+    pub fn new(options: &WindowOptions) -> Data {
+        Data {
+            update_delta: options.update_delta,
+            redraw: options.redraw,
+            size: options.size,
+        }
+    }
+    pub fn event_loop(self, mut window: super::Window, updates: u64) -> Vec<Frame> {
+        window.update_rectangle(self.size.x as i32, self.size.y as i32);
+        let mut event_handler = window.handler.take();
+        let mut frames = Vec::new();
+        let mut time = Duration::ZERO;
+        for update in 1..=updates {
+            if window.must_close {
+                break;
+            }
+            time += self.update_delta;
+            let start = Instant::now();
+            if let Some(handler) = &mut event_handler {
+                handler.handle_event(WindowEvent::Update(self.update_delta), &mut window);
+            }
+            window.meter.update(start.elapsed());
+            window.input.end_tick();
+            // there is no monitor, a frame per update is as fast as it goes
+            window.must_redraw |= self.redraw.is_due(1);
+            if window.must_redraw {
+                window.must_redraw = false;
+                let start = window.begin_draw();
+                if let Some(handler) = &mut event_handler {
+                    handler.handle_event(WindowEvent::Draw(0.), &mut window);
+                }
+                window.end_draw(start);
+                window.record_frame(time);
+                if let Some(graphics) = &window.graphics {
+                    let sprite = graphics.sprite().clone();
+                    frames.push(Frame {
+                        update,
+                        time,
+                        sprite,
+                    });
+                }
+            }
+            // the rates follow the simulated time
+            window.meter.advance(self.update_delta);
+        }
+        if !window.must_close {
+            if let Some(handler) = &mut event_handler {
+                handler.handle_event(WindowEvent::Exit, &mut window);
+            }
+        }
+        frames
+    }
+}
@@ -0,0 +1,284 @@
+use crate::Vector2;
+use std::collections::HashSet;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Key0,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Insert,
+    Home,
+    Delete,
+    End,
+    PageDown,
+    PageUp,
+    Left,
+    Up,
+    Right,
+    Down,
+    Backspace,
+    Enter,
+    Space,
+    Tab,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadEnter,
+    Apostrophe,
+    Backslash,
+    Comma,
+    Equals,
+    Grave,
+    LBracket,
+    RBracket,
+    Minus,
+    Period,
+    Semicolon,
+    Slash,
+    LShift,
+    RShift,
+    LControl,
+    RControl,
+    LAlt,
+    RAlt,
+    Other(u32),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u16),
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum ScrollDelta {
+    Lines(Vector2<f64>),
+    Pixels(Vector2<f64>),
+}
+
+pub struct Input {
+    keys_down: HashSet<Key>,
+    keys_pressed: HashSet<Key>,
+    keys_released: HashSet<Key>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    mouse_position: Vector2<i32>,
+    scroll_lines: Vector2<f64>,
+    scroll_pixels: Vector2<f64>,
+}
+
+impl Input {
+    pub(crate) fn new() -> Input {
+        Input {
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+            buttons_down: HashSet::new(),
+            buttons_pressed: HashSet::new(),
+            buttons_released: HashSet::new(),
+            mouse_position: Vector2::new(0, 0),
+            scroll_lines: Vector2::new(0.0, 0.0),
+            scroll_pixels: Vector2::new(0.0, 0.0),
+        }
+    }
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+    pub fn is_key_released(&self, key: Key) -> bool {
+        self.keys_released.contains(&key)
+    }
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+    pub fn is_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+    pub fn mouse_position(&self) -> Vector2<i32> {
+        self.mouse_position
+    }
+    pub fn scroll_lines(&self) -> Vector2<f64> {
+        self.scroll_lines
+    }
+    pub fn scroll_pixels(&self) -> Vector2<f64> {
+        self.scroll_pixels
+    }
+    pub(crate) fn press_key(&mut self, key: Key) {
+        // key repeat sends more presses without a release in between
+        if self.keys_down.insert(key) {
+            self.keys_pressed.insert(key);
+        }
+    }
+    pub(crate) fn release_key(&mut self, key: Key) {
+        if self.keys_down.remove(&key) {
+            self.keys_released.insert(key);
+        }
+    }
+    pub(crate) fn press_button(&mut self, button: MouseButton) {
+        if self.buttons_down.insert(button) {
+            self.buttons_pressed.insert(button);
+        }
+    }
+    pub(crate) fn release_button(&mut self, button: MouseButton) {
+        if self.buttons_down.remove(&button) {
+            self.buttons_released.insert(button);
+        }
+    }
+    pub(crate) fn move_mouse(&mut self, position: Vector2<i32>) {
+        self.mouse_position = position;
+    }
+    pub(crate) fn scroll(&mut self, delta: ScrollDelta) {
+        match delta {
+            ScrollDelta::Lines(lines) => {
+                self.scroll_lines.x += lines.x;
+                self.scroll_lines.y += lines.y;
+            }
+            ScrollDelta::Pixels(pixels) => {
+                self.scroll_pixels.x += pixels.x;
+                self.scroll_pixels.y += pixels.y;
+            }
+        }
+    }
+    pub(crate) fn release_all(&mut self) {
+        for key in self.keys_down.drain() {
+            self.keys_released.insert(key);
+        }
+        for button in self.buttons_down.drain() {
+            self.buttons_released.insert(button);
+        }
+    }
+    pub(crate) fn end_tick(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.scroll_lines = Vector2::new(0.0, 0.0);
+        self.scroll_pixels = Vector2::new(0.0, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_and_releases_last_one_tick() {
+        let mut input = Input::new();
+        input.press_key(Key::A);
+        // a repeat is not another press
+        input.press_key(Key::A);
+        assert!(input.is_key_down(Key::A));
+        assert!(input.is_key_pressed(Key::A));
+        input.end_tick();
+        input.press_key(Key::A);
+        assert!(input.is_key_down(Key::A));
+        assert!(!input.is_key_pressed(Key::A));
+        input.release_key(Key::A);
+        assert!(!input.is_key_down(Key::A));
+        assert!(input.is_key_released(Key::A));
+        input.end_tick();
+        assert!(!input.is_key_released(Key::A));
+        // nothing to release
+        input.release_key(Key::B);
+        assert!(!input.is_key_released(Key::B));
+
+        input.press_button(MouseButton::Left);
+        input.release_button(MouseButton::Left);
+        assert!(input.is_button_pressed(MouseButton::Left));
+        assert!(input.is_button_released(MouseButton::Left));
+        assert!(!input.is_button_down(MouseButton::Left));
+    }
+
+    #[test]
+    fn release_all_releases_what_is_down() {
+        let mut input = Input::new();
+        input.press_key(Key::Space);
+        input.press_button(MouseButton::Right);
+        input.end_tick();
+        input.release_all();
+        assert!(!input.is_key_down(Key::Space));
+        assert!(input.is_key_released(Key::Space));
+        assert!(!input.is_button_down(MouseButton::Right));
+        assert!(input.is_button_released(MouseButton::Right));
+        assert!(!input.is_key_released(Key::Enter));
+    }
+
+    #[test]
+    fn scrolling_adds_up_until_the_tick_ends() {
+        let mut input = Input::new();
+        input.scroll(ScrollDelta::Lines(Vector2::new(0.0, 1.0)));
+        input.scroll(ScrollDelta::Lines(Vector2::new(0.5, 2.0)));
+        input.scroll(ScrollDelta::Pixels(Vector2::new(3.0, 0.0)));
+        let lines = input.scroll_lines();
+        assert_eq!((lines.x, lines.y), (0.5, 3.0));
+        assert_eq!(input.scroll_pixels().x, 3.0);
+        input.end_tick();
+        assert_eq!(input.scroll_lines().y, 0.0);
+        assert_eq!(input.scroll_pixels().x, 0.0);
+    }
+}
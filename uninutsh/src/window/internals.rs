@@ -1,17 +1,24 @@
 use gl::types::GLuint;
-//use glutin::window::Fullscreen;
 use glutin::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{Event, WindowEvent},
+    event::{
+        ElementState, Event, KeyboardInput, MouseButton as GlutinMouseButton, MouseScrollDelta,
+        VirtualKeyCode, WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoop, EventLoopProxy},
-    window::{Window, WindowBuilder},
-    ContextBuilder, ContextWrapper, PossiblyCurrent,
+    monitor::MonitorHandle,
+    window::{Fullscreen as GlutinFullscreen, Window, WindowBuilder},
+    Api, ContextBuilder, ContextWrapper, GlProfile, GlRequest, PossiblyCurrent,
 };
 use std::{
     ffi::c_void,
     time::{Duration, Instant},
 };
 
+use super::input::{Key, MouseButton, ScrollDelta};
+use super::present::Presenter;
+use super::timestep::Timestep;
+use super::{EventHandler, Filter, Fullscreen, Redraw, WindowOptions};
 use crate::Vector2;
 
 #[derive(Debug, Clone, Copy)]
@@ -19,14 +26,22 @@ pub enum CustomEvent {}
 
 pub struct Data {
     update_delta: Duration,
+    max_updates: u32,
+    redraw: Redraw,
     texture: GLuint,
-    framebuffer: GLuint,
+    filter: Filter,
+    // whether the context is GL 3.3 core, good for the shader presentation
+    core: bool,
+    // where the window opened, for going fullscreen when it is not on any monitor
+    monitor: Option<MonitorHandle>,
+    // its index among the available monitors
+    monitor_index: Option<usize>,
     event_loop: Option<EventLoop<CustomEvent>>,
     proxy: Option<EventLoopProxy<CustomEvent>>,
     gl_window: ContextWrapper<PossiblyCurrent, Window>,
 }
 
-fn panic_gl(string: &str) {
+pub fn panic_gl(string: &str) {
     let error = unsafe { gl::GetError() };
     match error {
         gl::NO_ERROR => {}
@@ -57,37 +72,209 @@ fn panic_gl(string: &str) {
     }
 }
 
+fn dispatch(
+    event_handler: &mut Option<Box<dyn EventHandler>>,
+    event: super::WindowEvent,
+    window: &mut super::Window,
+) {
+    if let Some(handler) = event_handler {
+        handler.handle_event(event, window);
+    }
+}
+
+fn key(input: &KeyboardInput) -> Key {
+    let code = match input.virtual_keycode {
+        Some(code) => code,
+        None => return Key::Other(input.scancode),
+    };
+    match code {
+        VirtualKeyCode::Key1 => Key::Key1,
+        VirtualKeyCode::Key2 => Key::Key2,
+        VirtualKeyCode::Key3 => Key::Key3,
+        VirtualKeyCode::Key4 => Key::Key4,
+        VirtualKeyCode::Key5 => Key::Key5,
+        VirtualKeyCode::Key6 => Key::Key6,
+        VirtualKeyCode::Key7 => Key::Key7,
+        VirtualKeyCode::Key8 => Key::Key8,
+        VirtualKeyCode::Key9 => Key::Key9,
+        VirtualKeyCode::Key0 => Key::Key0,
+        VirtualKeyCode::A => Key::A,
+        VirtualKeyCode::B => Key::B,
+        VirtualKeyCode::C => Key::C,
+        VirtualKeyCode::D => Key::D,
+        VirtualKeyCode::E => Key::E,
+        VirtualKeyCode::F => Key::F,
+        VirtualKeyCode::G => Key::G,
+        VirtualKeyCode::H => Key::H,
+        VirtualKeyCode::I => Key::I,
+        VirtualKeyCode::J => Key::J,
+        VirtualKeyCode::K => Key::K,
+        VirtualKeyCode::L => Key::L,
+        VirtualKeyCode::M => Key::M,
+        VirtualKeyCode::N => Key::N,
+        VirtualKeyCode::O => Key::O,
+        VirtualKeyCode::P => Key::P,
+        VirtualKeyCode::Q => Key::Q,
+        VirtualKeyCode::R => Key::R,
+        VirtualKeyCode::S => Key::S,
+        VirtualKeyCode::T => Key::T,
+        VirtualKeyCode::U => Key::U,
+        VirtualKeyCode::V => Key::V,
+        VirtualKeyCode::W => Key::W,
+        VirtualKeyCode::X => Key::X,
+        VirtualKeyCode::Y => Key::Y,
+        VirtualKeyCode::Z => Key::Z,
+        VirtualKeyCode::Escape => Key::Escape,
+        VirtualKeyCode::F1 => Key::F1,
+        VirtualKeyCode::F2 => Key::F2,
+        VirtualKeyCode::F3 => Key::F3,
+        VirtualKeyCode::F4 => Key::F4,
+        VirtualKeyCode::F5 => Key::F5,
+        VirtualKeyCode::F6 => Key::F6,
+        VirtualKeyCode::F7 => Key::F7,
+        VirtualKeyCode::F8 => Key::F8,
+        VirtualKeyCode::F9 => Key::F9,
+        VirtualKeyCode::F10 => Key::F10,
+        VirtualKeyCode::F11 => Key::F11,
+        VirtualKeyCode::F12 => Key::F12,
+        VirtualKeyCode::Insert => Key::Insert,
+        VirtualKeyCode::Home => Key::Home,
+        VirtualKeyCode::Delete => Key::Delete,
+        VirtualKeyCode::End => Key::End,
+        VirtualKeyCode::PageDown => Key::PageDown,
+        VirtualKeyCode::PageUp => Key::PageUp,
+        VirtualKeyCode::Left => Key::Left,
+        VirtualKeyCode::Up => Key::Up,
+        VirtualKeyCode::Right => Key::Right,
+        VirtualKeyCode::Down => Key::Down,
+        VirtualKeyCode::Back => Key::Backspace,
+        VirtualKeyCode::Return => Key::Enter,
+        VirtualKeyCode::Space => Key::Space,
+        VirtualKeyCode::Tab => Key::Tab,
+        VirtualKeyCode::Numpad0 => Key::Numpad0,
+        VirtualKeyCode::Numpad1 => Key::Numpad1,
+        VirtualKeyCode::Numpad2 => Key::Numpad2,
+        VirtualKeyCode::Numpad3 => Key::Numpad3,
+        VirtualKeyCode::Numpad4 => Key::Numpad4,
+        VirtualKeyCode::Numpad5 => Key::Numpad5,
+        VirtualKeyCode::Numpad6 => Key::Numpad6,
+        VirtualKeyCode::Numpad7 => Key::Numpad7,
+        VirtualKeyCode::Numpad8 => Key::Numpad8,
+        VirtualKeyCode::Numpad9 => Key::Numpad9,
+        VirtualKeyCode::NumpadAdd => Key::NumpadAdd,
+        VirtualKeyCode::NumpadSubtract => Key::NumpadSubtract,
+        VirtualKeyCode::NumpadMultiply => Key::NumpadMultiply,
+        VirtualKeyCode::NumpadDivide => Key::NumpadDivide,
+        VirtualKeyCode::NumpadEnter => Key::NumpadEnter,
+        VirtualKeyCode::Apostrophe => Key::Apostrophe,
+        VirtualKeyCode::Backslash => Key::Backslash,
+        VirtualKeyCode::Comma => Key::Comma,
+        VirtualKeyCode::Equals => Key::Equals,
+        VirtualKeyCode::Grave => Key::Grave,
+        VirtualKeyCode::LBracket => Key::LBracket,
+        VirtualKeyCode::RBracket => Key::RBracket,
+        VirtualKeyCode::Minus => Key::Minus,
+        VirtualKeyCode::Period => Key::Period,
+        VirtualKeyCode::Semicolon => Key::Semicolon,
+        VirtualKeyCode::Slash => Key::Slash,
+        VirtualKeyCode::LShift => Key::LShift,
+        VirtualKeyCode::RShift => Key::RShift,
+        VirtualKeyCode::LControl => Key::LControl,
+        VirtualKeyCode::RControl => Key::RControl,
+        VirtualKeyCode::LAlt => Key::LAlt,
+        VirtualKeyCode::RAlt => Key::RAlt,
+        _ => Key::Other(input.scancode),
+    }
+}
+
+fn mouse_button(button: GlutinMouseButton) -> MouseButton {
+    match button {
+        GlutinMouseButton::Left => MouseButton::Left,
+        GlutinMouseButton::Right => MouseButton::Right,
+        GlutinMouseButton::Middle => MouseButton::Middle,
+        GlutinMouseButton::Other(index) => MouseButton::Other(index),
+    }
+}
+
+fn monitor(event_loop: &EventLoop<CustomEvent>, index: Option<usize>) -> Option<MonitorHandle> {
+    if let Some(monitor) = index.and_then(|index| event_loop.available_monitors().nth(index)) {
+        return Some(monitor);
+    }
+    event_loop
+        .primary_monitor()
+        .or_else(|| event_loop.available_monitors().next())
+}
+
+fn fullscreen(fullscreen: Fullscreen, monitor: Option<MonitorHandle>) -> Option<GlutinFullscreen> {
+    match fullscreen {
+        Fullscreen::Off => None,
+        Fullscreen::Borderless => Some(GlutinFullscreen::Borderless(monitor)),
+        Fullscreen::Exclusive => {
+            let mode = monitor.as_ref().and_then(|monitor| {
+                monitor.video_modes().max_by_key(|mode| {
+                    let pixels = mode.size().width * mode.size().height;
+                    (pixels, mode.bit_depth(), mode.refresh_rate())
+                })
+            });
+            match mode {
+                Some(mode) => Some(GlutinFullscreen::Exclusive(mode)),
+                None => Some(GlutinFullscreen::Borderless(monitor)),
+            }
+        }
+    }
+}
+
 impl Data {
-    pub fn new(title: String, size: Vector2<u32>, update_delta: Duration) -> Data {
+    pub fn new(options: &WindowOptions) -> Data {
         let el = EventLoop::<CustomEvent>::with_user_event();
         let proxy = Some(el.create_proxy());
-        let monitor = el.primary_monitor().unwrap();
-        let width = size.x; //
-        let height = size.y;
-        let x = monitor.size().width / 2 - width / 2;
-        let y = monitor.size().height / 2 - height / 2;
-        let window_builder = WindowBuilder::new()
-            //.with_fullscreen(Some(Fullscreen::Borderless(None)))
-            .with_title(title)
+        let monitor = monitor(&el, options.monitor);
+        let monitor_index = match &monitor {
+            Some(monitor) => el
+                .available_monitors()
+                .position(|available| available == *monitor),
+            None => None,
+        };
+        let width = options.size.x;
+        let height = options.size.y;
+        let mut window_builder = WindowBuilder::new()
+            .with_title(&options.title)
             .with_inner_size(PhysicalSize::new(width, height))
-            .with_position(PhysicalPosition::new(x, y))
-            .with_min_inner_size(PhysicalSize::new(512, 256));
-        let context_builder = ContextBuilder::new()
-            .build_windowed(window_builder, &el)
-            .unwrap();
+            .with_resizable(options.resizable)
+            .with_fullscreen(fullscreen(options.fullscreen, monitor.clone()));
+        if let Some(monitor) = &monitor {
+            // centered on the monitor
+            let position = monitor.position();
+            let size = monitor.size();
+            let x = position.x + (size.width as i32 - width as i32) / 2;
+            let y = position.y + (size.height as i32 - height as i32) / 2;
+            window_builder = window_builder.with_position(PhysicalPosition::new(x, y));
+        }
+        if let Some(min_size) = options.min_size {
+            let min_size = PhysicalSize::new(min_size.x, min_size.y);
+            window_builder = window_builder.with_min_inner_size(min_size);
+        }
+        let core_builder = ContextBuilder::new()
+            .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
+            .with_gl_profile(GlProfile::Core)
+            .with_vsync(options.vsync)
+            .build_windowed(window_builder.clone(), &el);
+        let (context_builder, core) = match core_builder {
+            Ok(context_builder) => (context_builder, true),
+            Err(_) => {
+                let context_builder = ContextBuilder::new()
+                    .with_vsync(options.vsync)
+                    .build_windowed(window_builder, &el)
+                    .unwrap();
+                (context_builder, false)
+            }
+        };
 
         // It is essential to make the context current before calling `gl::load_with`.
         let gl_window = unsafe { context_builder.make_current().unwrap() };
         let event_loop = Some(el);
         // Load the OpenGL function pointers
         gl::load_with(|symbol| gl_window.get_proc_address(symbol));
-        let mut framebuffer = 0;
-        unsafe {
-            gl::GenFramebuffers(1, &mut framebuffer);
-            panic_gl("gl::GenFramebuffers");
-            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, framebuffer);
-            panic_gl("gl::BindFramebuffer");
-        }
         let mut texture = 0;
         unsafe {
             gl::GenTextures(1, &mut texture);
@@ -98,13 +285,21 @@ impl Data {
         }
         Data {
             texture,
-            framebuffer,
+            filter: options.filter,
+            core,
+            monitor,
+            monitor_index,
             event_loop,
             proxy,
             gl_window,
-            update_delta,
+            update_delta: options.update_delta,
+            max_updates: options.max_updates,
+            redraw: options.redraw,
         }
     }
+    pub fn monitor_index(&self) -> Option<usize> {
+        self.monitor_index
+    }
     pub fn event_loop(mut self, mut window: super::Window) {
         let event_loop = self.event_loop.take().unwrap();
         let _proxy = self.proxy.take().unwrap();
@@ -117,7 +312,8 @@ impl Data {
         let size = self.gl_window.window().inner_size();
         window.update_rectangle(size.width as i32, size.height as i32);
         let mut event_handler = window.handler.take();
-        let pixels = window.pixels().expect("can not take the pixels");
+        let (pixels, _) = window.take_upload();
+        let pixels = pixels.expect("can not take the pixels");
         unsafe {
             gl::TexImage2D(
                 gl::TEXTURE_2D,
@@ -132,17 +328,17 @@ impl Data {
             );
             panic_gl("gl::TexImage2D");
 
-            gl::FramebufferTexture2D(
-                gl::READ_FRAMEBUFFER,
-                gl::COLOR_ATTACHMENT0,
-                gl::TEXTURE_2D,
-                self.texture,
-                0,
-            );
-            panic_gl("gl::FramebufferTexture2D");
+            // the changed rows are uploaded straight out of the whole buffer
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, sprite_width);
+            panic_gl("gl::PixelStorei");
         }
         window.return_pixels(Some(pixels));
-        let mut last_update_instant = Instant::now();
+        let presenter = Presenter::new(self.texture, self.filter, self.core);
+        window.shader_presented = presenter.is_shader();
+        window.shader_error = presenter.error().map(String::from);
+        let mut timestep = Timestep::new(self.update_delta, self.max_updates);
+        let mut last_instant = Instant::now();
+        let start_instant = last_instant;
         event_loop.run(move |event, _el_window_target, control_flow| {
             match event {
                 Event::UserEvent(_event) => (),
@@ -150,27 +346,95 @@ impl Data {
                     return;
                 }
                 Event::WindowEvent { event, .. } => match event {
-                    WindowEvent::CloseRequested => match &mut event_handler {
-                        Some(handler) => {
-                            handler.handle_event(super::WindowEvent::Exit, &mut window);
-                        }
-                        None => {}
-                    },
+                    WindowEvent::CloseRequested => {
+                        dispatch(&mut event_handler, super::WindowEvent::Exit, &mut window);
+                    }
                     WindowEvent::Resized(size) => {
                         window.update_rectangle(size.width as i32, size.height as i32);
                     }
+                    WindowEvent::Focused(false) => {
+                        window.input.release_all();
+                    }
+                    WindowEvent::KeyboardInput { input, .. } => {
+                        let key = key(&input);
+                        let event = match input.state {
+                            ElementState::Pressed => {
+                                window.input.press_key(key);
+                                super::WindowEvent::KeyPressed(key)
+                            }
+                            ElementState::Released => {
+                                window.input.release_key(key);
+                                super::WindowEvent::KeyReleased(key)
+                            }
+                        };
+                        dispatch(&mut event_handler, event, &mut window);
+                    }
+                    WindowEvent::MouseInput { state, button, .. } => {
+                        let button = mouse_button(button);
+                        let position = window.input.mouse_position();
+                        let event = match state {
+                            ElementState::Pressed => {
+                                window.input.press_button(button);
+                                super::WindowEvent::MousePressed(button, position)
+                            }
+                            ElementState::Released => {
+                                window.input.release_button(button);
+                                super::WindowEvent::MouseReleased(button, position)
+                            }
+                        };
+                        dispatch(&mut event_handler, event, &mut window);
+                    }
+                    WindowEvent::CursorMoved { position, .. } => {
+                        let position = window.screen_to_graphics(position.x, position.y);
+                        window.input.move_mouse(position);
+                        let event = super::WindowEvent::MouseMoved(position);
+                        dispatch(&mut event_handler, event, &mut window);
+                    }
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        let delta = match delta {
+                            MouseScrollDelta::LineDelta(x, y) => {
+                                ScrollDelta::Lines(Vector2::new(x as f64, y as f64))
+                            }
+                            MouseScrollDelta::PixelDelta(position) => {
+                                let scale = window.screen_to_graphics_scale();
+                                ScrollDelta::Pixels(Vector2::new(
+                                    position.x * scale,
+                                    position.y * scale,
+                                ))
+                            }
+                        };
+                        window.input.scroll(delta);
+                        let event = super::WindowEvent::Scroll(delta);
+                        dispatch(&mut event_handler, event, &mut window);
+                    }
                     _ => (),
                 },
-                Event::RedrawRequested(_) => {
-                    match &mut event_handler {
-                        Some(handler) => {
-                            handler.handle_event(super::WindowEvent::Draw, &mut window);
-                        }
-                        None => {}
+                Event::MainEventsCleared => {
+                    let now = Instant::now();
+                    window.meter.advance(now - last_instant);
+                    timestep.advance(now - last_instant);
+                    last_instant = now;
+                    while !window.must_close && timestep.next_update() {
+                        let start = Instant::now();
+                        let event = super::WindowEvent::Update(self.update_delta);
+                        dispatch(&mut event_handler, event, &mut window);
+                        window.meter.update(start.elapsed());
+                        window.input.end_tick();
                     }
-                    let pixels = window
-                        .pixels()
-                        .expect("can not take the pixels for updating");
+                    window.must_redraw |= self.redraw.is_due(timestep.updates());
+                }
+                Event::RedrawRequested(_) => {
+                    let pending = timestep.accumulated() + last_instant.elapsed();
+                    let alpha = pending.as_secs_f64() / self.update_delta.as_secs_f64();
+                    let event = super::WindowEvent::Draw(alpha.min(1.));
+                    let start = window.begin_draw();
+                    dispatch(&mut event_handler, event, &mut window);
+                    window.end_draw(start);
+                    // drawn before the timer starts, so it doesn't count as upload time
+                    let overlay = window.stats_overlay();
+                    let start = Instant::now();
+                    let (pixels, changed) = window.take_upload();
+                    let pixels = pixels.expect("can not take the pixels for updating");
                     unsafe {
                         gl::ClearColor(0. / 2., 0. / 2., 0. / 2., 1.);
                         panic_gl("gl::ClearColor");
@@ -178,66 +442,90 @@ impl Data {
                         gl::Clear(gl::COLOR_BUFFER_BIT);
                         panic_gl("gl::Clear");
 
-                        gl::TexSubImage2D(
-                            gl::TEXTURE_2D,
-                            0,
-                            0,
-                            0,
-                            sprite_width,
-                            sprite_height,
-                            gl::RGBA,
-                            gl::UNSIGNED_BYTE,
-                            pixels.as_ptr() as *const c_void,
-                        );
-                        panic_gl("gl::TexSubImage2D");
+                        if let Some(changed) = changed {
+                            // the pixels are upside down, as GL wants them
+                            let x = changed.position.x;
+                            let y = sprite_height - changed.position.y - changed.size.y;
+                            let start = (y * sprite_width + x) as usize * 4;
+                            gl::TexSubImage2D(
+                                gl::TEXTURE_2D,
+                                0,
+                                x,
+                                y,
+                                changed.size.x,
+                                changed.size.y,
+                                gl::RGBA,
+                                gl::UNSIGNED_BYTE,
+                                pixels[start..].as_ptr() as *const c_void,
+                            );
+                            panic_gl("gl::TexSubImage2D");
+                        }
                         window.return_pixels(Some(pixels));
 
-                        gl::BlitNamedFramebuffer(
-                            self.framebuffer,
-                            0,
-                            0,
-                            0,
-                            sprite_width,
-                            sprite_height,
-                            window.rectangle.position.x,
-                            window.rectangle.position.y,
-                            window.rectangle.position.x + window.rectangle.size.x,
-                            window.rectangle.position.y + window.rectangle.size.y,
-                            gl::COLOR_BUFFER_BIT,
-                            gl::NEAREST,
-                        );
-                        panic_gl("gl::BlitNamedFramebuffer");
+                        if let Some((size, pixels)) = overlay {
+                            let (width, height) = (size.x as i32, size.y as i32);
+                            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, width);
+                            gl::TexSubImage2D(
+                                gl::TEXTURE_2D,
+                                0,
+                                0,
+                                sprite_height - height,
+                                width,
+                                height,
+                                gl::RGBA,
+                                gl::UNSIGNED_BYTE,
+                                pixels.as_ptr() as *const c_void,
+                            );
+                            panic_gl("gl::TexSubImage2D");
+                            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, sprite_width);
+                        }
                     }
+                    window.meter.upload(start.elapsed());
+                    let start = Instant::now();
+                    presenter.present(sprite_width, sprite_height, window.rectangle);
                     self.gl_window.swap_buffers().unwrap();
+                    window.meter.swap(start.elapsed());
+                    window.record_frame(start_instant.elapsed());
                     //println!("redraw")
                 }
                 _ => (),
             }
-            let delta = last_update_instant.elapsed();
-            if delta >= self.update_delta && !window.must_close {
-                match &mut event_handler {
-                    Some(handler) => {
-                        handler.handle_event(super::WindowEvent::Update(delta), &mut window);
-                    }
-                    None => {}
-                }
-
-                last_update_instant = Instant::now();
-            }
             if window.must_redraw {
                 self.gl_window.window().request_redraw();
                 window.must_redraw = false;
             }
+            if window.must_set_fullscreen {
+                window.must_set_fullscreen = false;
+                let gl_window = self.gl_window.window();
+                let monitor = gl_window.current_monitor().or_else(|| self.monitor.clone());
+                gl_window.set_fullscreen(fullscreen(window.fullscreen, monitor));
+            }
             if window.must_close {
                 *control_flow = ControlFlow::Exit;
             }
             match control_flow {
                 ControlFlow::Exit => (),
+                _ if self.redraw == Redraw::Vsync => *control_flow = ControlFlow::Poll,
                 _ => {
-                    *control_flow =
-                        ControlFlow::WaitUntil(Instant::now() + Duration::from_millis(1));
+                    // asleep until the next update is due
+                    let next_update = timestep.until_next_update();
+                    *control_flow = ControlFlow::WaitUntil(last_instant + next_update);
                 }
             }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fullscreen_without_a_monitor_is_borderless() {
+        assert_eq!(fullscreen(Fullscreen::Off, None), None);
+        let borderless = Some(GlutinFullscreen::Borderless(None));
+        assert_eq!(fullscreen(Fullscreen::Borderless, None), borderless);
+        // no video modes to pick from
+        assert_eq!(fullscreen(Fullscreen::Exclusive, None), borderless);
+    }
+}
@@ -1,33 +1,127 @@
+mod headless;
+pub mod input;
 mod internals;
-use crate::image::Graphics;
+mod present;
+pub mod stats;
+mod timestep;
+use crate::image::{font::Font, recorder::Recorder, BlendMode, Error, Graphics, Sprite};
 use crate::{Rectangle, Vector2};
-use std::time::Duration;
+use input::{Input, Key, MouseButton, ScrollDelta};
+use stats::{Meter, Stats};
+use std::time::{Duration, Instant};
 
 pub enum WindowEvent {
+    // always the update delta of the options
     Update(Duration),
-    Draw,
+    // how far, from 0 to 1, the time is between the last update and the next one
+    Draw(f64),
     Exit,
+    KeyPressed(Key),
+    KeyReleased(Key),
+    MousePressed(MouseButton, Vector2<i32>),
+    MouseReleased(MouseButton, Vector2<i32>),
+    MouseMoved(Vector2<i32>),
+    Scroll(ScrollDelta),
 }
 
 pub trait EventHandler {
     fn handle_event(&mut self, event: WindowEvent, window: &mut Window);
 }
 
+// How the graphics are scaled up to the window.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    // bilinear with scanlines and an aperture grille, only bilinear without GL 3.3
+    Crt,
+}
+
+// How big the graphics are drawn in the window, always centered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Scaling {
+    // as big as the window allows keeping the aspect ratio
+    Fit,
+    // as big as the window allows in whole multiples, so every pixel is as wide as the others;
+    // `Fit` when the window is smaller than the graphics
+    Integer,
+    // all the window, whatever the aspect ratio
+    Stretch,
+    // this many window pixels per graphics pixel, cropped when the window is smaller
+    Fixed(u32),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fullscreen {
+    Off,
+    // a window without decorations covering the monitor
+    Borderless,
+    // the largest video mode of the monitor, borderless when it has none
+    Exclusive,
+}
+
+// When the graphics are drawn again, `Window::redraw` works with any of them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Redraw {
+    // only after `Window::redraw`
+    OnDemand,
+    // after the updates that ran in a frame
+    EveryUpdate,
+    // every frame, paced by vsync; without it as fast as possible
+    Vsync,
+}
+
 pub struct WindowOptions {
     pub update_delta: Duration,
+    // updates run back to back when late, the ones beyond this are skipped
+    pub max_updates: u32,
+    pub redraw: Redraw,
     pub title: String,
     pub size: Vector2<u32>,
     pub graphics_size: Vector2<u32>,
+    pub filter: Filter,
+    pub scaling: Scaling,
+    pub fullscreen: Fullscreen,
+    // index in the list of monitors, the primary one when None or out of range,
+    // `Window::monitor` tells which one was used
+    pub monitor: Option<usize>,
+    pub vsync: bool,
+    pub resizable: bool,
+    pub min_size: Option<Vector2<u32>>,
+}
+
+// A frame drawn by `run_headless`.
+pub struct Frame {
+    // how many updates ran before it was drawn, counting from 1
+    pub update: u64,
+    // the simulated time at that point, `update` times the update delta
+    pub time: Duration,
+    pub sprite: Sprite,
 }
 
 pub struct Window {
     size_ratio: f64,
     rectangle: Rectangle<i32>,
+    window_size: Vector2<i32>,
+    graphics_size: Vector2<u32>,
+    scaling: Scaling,
+    fullscreen: Fullscreen,
+    input: Input,
     graphics: Option<Graphics>,
     internals: Option<internals::Data>,
+    headless: Option<headless::Data>,
+    recorder: Option<Recorder>,
+    meter: Meter,
+    show_stats: bool,
+    // the stats over a copy of the corner they cover, kept out of the graphics
+    overlay: Option<Graphics>,
+    monitor: Option<usize>,
+    shader_presented: bool,
+    shader_error: Option<String>,
     handler: Option<Box<dyn EventHandler>>,
     must_close: bool,
     must_redraw: bool,
+    must_set_fullscreen: bool,
 }
 
 impl Window {
@@ -53,30 +147,179 @@ impl Window {
         options: WindowOptions,
         handler: Box<dyn EventHandler>,
     ) -> Window {
-        let internals = Some(internals::Data::new(options.title, options.size, options.update_delta));
-        let graphics = Some(Graphics::new(options.graphics_size.x, options.graphics_size.y));
+        let internals = internals::Data::new(&options);
+        let monitor = internals.monitor_index();
+        let mut window = Window::with_backend(&options, Some(internals), None, handler);
+        window.monitor = monitor;
+        window
+    }
+    pub fn headless(options: WindowOptions, handler: Box<dyn EventHandler>) -> Window {
+        let headless = Some(headless::Data::new(&options));
+        Window::with_backend(&options, None, headless, handler)
+    }
+    fn with_backend(
+        options: &WindowOptions,
+        internals: Option<internals::Data>,
+        headless: Option<headless::Data>,
+        handler: Box<dyn EventHandler>,
+    ) -> Window {
+        let graphics_size = options.graphics_size;
+        let graphics = Some(Graphics::new(graphics_size.x, graphics_size.y));
         let rectangle = Rectangle::new(0, 0, 0, 0);
-        let size_ratio = options.graphics_size.x as f64 / options.graphics_size.y as f64;
+        let size_ratio = graphics_size.x as f64 / graphics_size.y as f64;
         Window {
             size_ratio,
             rectangle,
+            window_size: Vector2::new(0, 0),
+            graphics_size,
+            scaling: options.scaling,
+            fullscreen: options.fullscreen,
+            input: Input::new(),
             graphics,
             internals,
+            headless,
+            recorder: None,
+            meter: Meter::default(),
+            show_stats: false,
+            overlay: None,
+            monitor: None,
+            shader_presented: false,
+            shader_error: None,
             handler: Some(handler),
             must_close: false,
             must_redraw: false,
+            must_set_fullscreen: false,
         }
     }
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.input.is_key_down(key)
+    }
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.input.is_button_down(button)
+    }
+    pub fn mouse_position(&self) -> Vector2<i32> {
+        self.input.mouse_position()
+    }
+    pub fn is_headless(&self) -> bool {
+        self.headless.is_some()
+    }
+    // The index of the monitor the window opened on, not the one in the options when
+    // that does not exist; None when headless or no monitor is known.
+    pub fn monitor(&self) -> Option<usize> {
+        self.monitor
+    }
+    // Whether the graphics go through the shader quad rather than a framebuffer blit,
+    // known once the event loop started.
+    pub fn is_shader_presented(&self) -> bool {
+        self.shader_presented
+    }
+    // The compile or link log when the shader was tried and failed.
+    pub fn shader_error(&self) -> Option<&str> {
+        self.shader_error.as_deref()
+    }
     pub fn event_loop(mut self) {
-        let internals = self.internals.take().unwrap();
+        let internals = self
+            .internals
+            .take()
+            .expect("Window::event_loop on a headless window, use Window::run_headless");
         internals.event_loop(self);
     }
+    pub fn run_headless(mut self, updates: u64) -> Vec<Frame> {
+        let headless = self
+            .headless
+            .take()
+            .expect("Window::run_headless on a window created with Window::new");
+        headless.event_loop(self, updates)
+    }
+    // Every frame presented from now on goes to `recorder`, replacing any previous one.
+    pub fn start_recording(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+    pub fn stop_recording(&mut self) -> Option<Result<usize, Error>> {
+        self.recorder.take().map(Recorder::finish)
+    }
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+    fn record_frame(&mut self, time: Duration) {
+        if let (Some(recorder), Some(graphics)) = (&mut self.recorder, &self.graphics) {
+            recorder.record(graphics.sprite(), time);
+        }
+    }
+    pub fn stats(&self) -> Stats {
+        self.meter.stats()
+    }
+    // Shows the stats over the presented graphics, the graphics, recordings and
+    // headless frames stay without them. Draw them yourself with `Stats::draw`.
+    pub fn show_stats(&mut self, show: bool) {
+        self.show_stats = show;
+    }
+    pub fn is_showing_stats(&self) -> bool {
+        self.show_stats
+    }
+    fn begin_draw(&mut self) -> Instant {
+        // conversions done while updating are not part of the frame
+        if let Some(graphics) = &mut self.graphics {
+            graphics.take_convert_time();
+        }
+        Instant::now()
+    }
+    fn end_draw(&mut self, start: Instant) {
+        if let Some(graphics) = &mut self.graphics {
+            let convert = graphics.take_convert_time();
+            self.meter.draw(start.elapsed(), convert);
+        }
+    }
+    // The upside down pixels of the stats box to upload over the top left corner,
+    // which is marked to be uploaded again from the graphics next time.
+    fn stats_overlay(&mut self) -> Option<(Vector2<u32>, Vec<u8>)> {
+        let graphics = match &mut self.graphics {
+            Some(graphics) if self.show_stats => graphics,
+            _ => return None,
+        };
+        let stats = self.meter.stats();
+        let size = match &self.overlay {
+            Some(overlay) => stats.size(overlay.font()),
+            None => stats.size(&Font::new()),
+        };
+        let size = Vector2::new(size.x.min(graphics.width()), size.y.min(graphics.height()));
+        let reusable = match &self.overlay {
+            Some(overlay) => overlay.width() == size.x && overlay.height() == size.y,
+            None => false,
+        };
+        if !reusable {
+            self.overlay = Some(Graphics::new(size.x, size.y));
+        }
+        let overlay = self.overlay.as_mut()?;
+        overlay.set_blend_mode(BlendMode::Replace);
+        let region = Rectangle::new(0, 0, size.x, size.y);
+        overlay.put_sprite_region(graphics.sprite(), region, 0, 0);
+        overlay.set_blend_mode(BlendMode::Normal);
+        stats.draw(overlay);
+        overlay.apply();
+        graphics.mark_changed(Rectangle::new(0, 0, size.x as i32, size.y as i32));
+        let (pixels, _) = overlay.take_upload();
+        let copy = pixels.clone()?;
+        overlay.return_pixels(pixels);
+        Some((size, copy))
+    }
     pub fn close(&mut self) {
         self.must_close = true;
     }
     pub fn redraw(&mut self) {
         self.must_redraw = true;
     }
+    // Takes effect after the current event, on the monitor the window is in.
+    pub fn set_fullscreen(&mut self, fullscreen: Fullscreen) {
+        self.fullscreen = fullscreen;
+        self.must_set_fullscreen = true;
+    }
+    pub fn fullscreen(&self) -> Fullscreen {
+        self.fullscreen
+    }
     fn excess_width(&self, window_size_ratio: f64) -> bool {
         if window_size_ratio > self.size_ratio {
             return true;
@@ -84,6 +327,34 @@ impl Window {
         return false;
     }
     fn update_rectangle(&mut self, width: i32, height: i32) {
+        self.window_size = Vector2::new(width, height);
+        let graphics_width = self.graphics_size.x as i32;
+        let graphics_height = self.graphics_size.y as i32;
+        let size = match self.scaling {
+            Scaling::Fit => {
+                self.fit_rectangle(width, height);
+                return;
+            }
+            Scaling::Integer => {
+                let scale = (width / graphics_width).min(height / graphics_height);
+                // a window smaller than the graphics gets them fitted rather than cropped
+                if scale == 0 {
+                    self.fit_rectangle(width, height);
+                    return;
+                }
+                Vector2::new(graphics_width * scale, graphics_height * scale)
+            }
+            Scaling::Stretch => Vector2::new(width, height),
+            Scaling::Fixed(scale) => {
+                let scale = scale.max(1) as i32;
+                Vector2::new(graphics_width * scale, graphics_height * scale)
+            }
+        };
+        self.rectangle.size = size;
+        self.rectangle.position.x = (width - size.x) / 2;
+        self.rectangle.position.y = (height - size.y) / 2;
+    }
+    fn fit_rectangle(&mut self, width: i32, height: i32) {
         let window_size_ratio = width as f64 / height as f64;
         if self.excess_width(window_size_ratio) {
             self.rectangle.position.y = 0;
@@ -99,12 +370,52 @@ impl Window {
             self.rectangle.position.y = excess / 2;
         }
     }
+    // Where the graphics are drawn, in window pixels from the top left corner.
+    pub fn viewport(&self) -> Rectangle<i32> {
+        // the rectangle is in GL coordinates, with y growing upwards
+        let top = self.window_size.y - self.rectangle.position.y - self.rectangle.size.y;
+        Rectangle::new(
+            self.rectangle.position.x,
+            top,
+            self.rectangle.size.x,
+            self.rectangle.size.y,
+        )
+    }
+    // Window pixels per graphics pixel, the same on both axes unless stretched.
+    pub fn scale(&self) -> Vector2<f64> {
+        Vector2::new(
+            self.rectangle.size.x as f64 / self.graphics_size.x as f64,
+            self.rectangle.size.y as f64 / self.graphics_size.y as f64,
+        )
+    }
+    pub fn scaling(&self) -> Scaling {
+        self.scaling
+    }
+    pub fn screen_to_graphics(&self, x: f64, y: f64) -> Vector2<i32> {
+        let viewport = self.viewport();
+        let width = viewport.size.x.max(1) as f64;
+        let height = viewport.size.y.max(1) as f64;
+        let left = viewport.position.x as f64;
+        let top = viewport.position.y as f64;
+        let graphics_x = (x - left) * self.graphics_size.x as f64 / width;
+        let graphics_y = (y - top) * self.graphics_size.y as f64 / height;
+        Vector2::new(graphics_x.floor() as i32, graphics_y.floor() as i32)
+    }
+    fn screen_to_graphics_scale(&self) -> f64 {
+        self.graphics_size.x as f64 / self.rectangle.size.x.max(1) as f64
+    }
     pub fn pixels(&mut self) -> Option<Vec<u8>> {
         match &mut self.graphics {
             Some(graphics) => graphics.pixels(),
             None => None,
         }
     }
+    fn take_upload(&mut self) -> (Option<Vec<u8>>, Option<Rectangle<i32>>) {
+        match &mut self.graphics {
+            Some(graphics) => graphics.take_upload(),
+            None => (None, None),
+        }
+    }
     pub fn return_pixels(&mut self, pixels: Option<Vec<u8>>) {
         match &mut self.graphics {
             Some(graphics) => {
@@ -114,3 +425,212 @@ impl Window {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Color;
+
+    struct Fill(Color);
+
+    impl EventHandler for Fill {
+        fn handle_event(&mut self, event: WindowEvent, window: &mut Window) {
+            if let WindowEvent::Draw(_) = event {
+                let mut graphics = window.graphics().unwrap();
+                graphics.set_color(self.0);
+                graphics.fill_rectangle(0, 0, 64, 64);
+                graphics.apply();
+                window.return_graphics(Some(graphics));
+            }
+        }
+    }
+
+    fn options(redraw: Redraw) -> WindowOptions {
+        WindowOptions {
+            update_delta: Duration::from_millis(10),
+            max_updates: 4,
+            redraw,
+            title: String::from("test"),
+            size: Vector2::new(128, 128),
+            graphics_size: Vector2::new(64, 64),
+            filter: Filter::Nearest,
+            scaling: Scaling::Fit,
+            fullscreen: Fullscreen::Off,
+            monitor: None,
+            vsync: false,
+            resizable: false,
+            min_size: None,
+        }
+    }
+
+    // Draws a pixel as far right as the updates so far.
+    struct Steps {
+        updates: u32,
+    }
+
+    impl EventHandler for Steps {
+        fn handle_event(&mut self, event: WindowEvent, window: &mut Window) {
+            match event {
+                WindowEvent::Update(delta) => {
+                    assert_eq!(delta, Duration::from_millis(10));
+                    self.updates += 1;
+                    // every other update
+                    if self.updates & 1 == 0 {
+                        window.redraw();
+                    }
+                }
+                WindowEvent::Draw(alpha) => {
+                    assert_eq!(alpha, 0.);
+                    let mut graphics = window.graphics().unwrap();
+                    graphics.set_color(Color::from_rgb(255, 0, 0));
+                    graphics.draw_pixel(self.updates as i32, 0);
+                    graphics.apply();
+                    window.return_graphics(Some(graphics));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn headless_frames() {
+        let handler = Box::new(Steps { updates: 0 });
+        let frames = Window::headless(options(Redraw::OnDemand), handler).run_headless(6);
+        let updates: Vec<u64> = frames.iter().map(|frame| frame.update).collect();
+        assert_eq!(updates, vec![2, 4, 6]);
+        let times: Vec<Duration> = frames.iter().map(|frame| frame.time).collect();
+        let milliseconds = |value| Duration::from_millis(value);
+        assert_eq!(
+            times,
+            vec![milliseconds(20), milliseconds(40), milliseconds(60)]
+        );
+        let last = &frames[2].sprite;
+        for x in [2, 4, 6] {
+            assert_eq!(last.get(x, 0), [255, 0, 0, 255]);
+        }
+        assert_eq!(last.get(3, 0), [255, 255, 255, 255]);
+        assert_eq!(frames[0].sprite.get(4, 0), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn stats_overlay_leaves_the_graphics_alone() {
+        let blue = Color::from_rgb(0, 0, 255);
+        let mut window = Window::headless(options(Redraw::EveryUpdate), Box::new(Fill(blue)));
+        window.show_stats(true);
+        let frames = window.run_headless(3);
+        assert_eq!(frames.len(), 3);
+        for frame in &frames {
+            assert_eq!(frame.sprite.get(0, 0), [0, 0, 255, 255]);
+        }
+
+        let mut window = Window::headless(options(Redraw::OnDemand), Box::new(Fill(blue)));
+        let (pixels, _) = window.take_upload();
+        window.return_pixels(pixels);
+        let before = window.graphics.as_ref().unwrap().sprite().get(0, 0);
+        assert!(window.stats_overlay().is_none());
+        window.show_stats(true);
+        let (size, pixels) = window.stats_overlay().unwrap();
+        let expected = window.stats().size(&Font::new());
+        assert_eq!((size.x, size.y), (expected.x, expected.y));
+        assert_eq!(pixels.len(), (size.x * size.y * 4) as usize);
+        let graphics = window.graphics.as_mut().unwrap();
+        assert_eq!(graphics.sprite().get(0, 0), before);
+        // the covered corner goes up again once the stats are hidden
+        let (_, changed) = graphics.take_upload();
+        let changed = changed.unwrap();
+        assert_eq!(
+            (changed.size.x, changed.size.y),
+            (size.x as i32, size.y as i32)
+        );
+    }
+
+    // A headless window with 64x32 graphics, resized to `width` x `height`.
+    fn resized(scaling: Scaling, width: i32, height: i32) -> Window {
+        let mut options = options(Redraw::OnDemand);
+        options.graphics_size = Vector2::new(64, 32);
+        options.scaling = scaling;
+        let mut window = Window::headless(options, Box::new(Fill(Color::from_rgb(0, 0, 0))));
+        window.update_rectangle(width, height);
+        window
+    }
+
+    fn viewport(scaling: Scaling, width: i32, height: i32) -> (i32, i32, i32, i32, f64, f64) {
+        let window = resized(scaling, width, height);
+        let viewport = window.viewport();
+        let scale = window.scale();
+        (
+            viewport.position.x,
+            viewport.position.y,
+            viewport.size.x,
+            viewport.size.y,
+            scale.x,
+            scale.y,
+        )
+    }
+
+    #[test]
+    fn scaling_policies() {
+        assert_eq!(viewport(Scaling::Fit, 128, 128), (0, 32, 128, 64, 2., 2.));
+        assert_eq!(
+            viewport(Scaling::Fit, 300, 100),
+            (50, 0, 200, 100, 3.125, 3.125)
+        );
+        assert_eq!(
+            viewport(Scaling::Integer, 200, 101),
+            (4, 3, 192, 96, 3., 3.)
+        );
+        assert_eq!(
+            viewport(Scaling::Integer, 127, 127),
+            (31, 48, 64, 32, 1., 1.)
+        );
+        // too small for even one whole multiple
+        assert_eq!(
+            viewport(Scaling::Integer, 50, 20),
+            (5, 0, 40, 20, 0.625, 0.625)
+        );
+        assert_eq!(
+            viewport(Scaling::Stretch, 100, 300),
+            (0, 0, 100, 300, 1.5625, 9.375)
+        );
+        assert_eq!(
+            viewport(Scaling::Fixed(2), 100, 50),
+            (-14, -7, 128, 64, 2., 2.)
+        );
+        assert_eq!(viewport(Scaling::Fixed(0), 64, 32), (0, 0, 64, 32, 1., 1.));
+    }
+
+    #[test]
+    fn screen_to_graphics_skips_the_bars() {
+        let to_graphics = |window: &Window, x, y| {
+            let position = window.screen_to_graphics(x, y);
+            (position.x, position.y)
+        };
+        // bars left and right
+        let window = resized(Scaling::Fit, 300, 100);
+        assert_eq!(to_graphics(&window, 50., 0.), (0, 0));
+        assert_eq!(to_graphics(&window, 49.9, 50.), (-1, 16));
+        assert_eq!(to_graphics(&window, 249.9, 99.9), (63, 31));
+        assert_eq!(to_graphics(&window, 250., 100.), (64, 32));
+        assert_eq!(window.screen_to_graphics_scale(), 0.32);
+        // bars above and below
+        let window = resized(Scaling::Fit, 128, 128);
+        assert_eq!(to_graphics(&window, 0., 31.9), (0, -1));
+        assert_eq!(to_graphics(&window, 0., 32.), (0, 0));
+        assert_eq!(to_graphics(&window, 127.9, 95.9), (63, 31));
+    }
+
+    #[test]
+    fn fullscreen_changes_wait_for_the_event_loop() {
+        let mut options = options(Redraw::OnDemand);
+        options.fullscreen = Fullscreen::Borderless;
+        options.monitor = Some(3);
+        let mut window = Window::headless(options, Box::new(Fill(Color::from_rgb(0, 0, 0))));
+        assert_eq!(window.fullscreen(), Fullscreen::Borderless);
+        assert!(!window.must_set_fullscreen);
+        // no monitors without a window
+        assert_eq!(window.monitor(), None);
+        window.set_fullscreen(Fullscreen::Off);
+        assert_eq!(window.fullscreen(), Fullscreen::Off);
+        assert!(window.must_set_fullscreen);
+    }
+}
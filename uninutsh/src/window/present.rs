@@ -0,0 +1,276 @@
+use gl::types::{GLchar, GLenum, GLint, GLuint};
+use std::{ffi::CString, ptr};
+
+use super::internals::panic_gl;
+use super::Filter;
+use crate::Rectangle;
+
+const VERTEX_SHADER: &str = "#version 330 core
+layout(location = 0) in vec2 corner;
+out vec2 position;
+void main() {
+    position = corner;
+    gl_Position = vec4(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+";
+
+// The texture rows are already upside down, so the corners map straight to it.
+const FRAGMENT_SHADER: &str = "#version 330 core
+in vec2 position;
+out vec4 color;
+uniform sampler2D graphics;
+uniform vec2 graphics_size;
+uniform bool crt;
+void main() {
+    vec3 texel = texture(graphics, position).rgb;
+    if (crt) {
+        // dark gaps between the rows and a faint aperture grille between the columns
+        float row = fract(position.y * graphics_size.y);
+        float scanline = 0.55 + 0.45 * sin(row * 3.14159265);
+        vec3 mask = vec3(0.8);
+        mask[int(mod(gl_FragCoord.x, 3.0))] = 1.0;
+        texel = min(texel * scanline * mask * 1.25, vec3(1.0));
+    }
+    color = vec4(texel, 1.0);
+}
+";
+
+const CORNERS: [f32; 8] = [0., 0., 1., 0., 0., 1., 1., 1.];
+
+// How the graphics texture reaches the window: a textured quad when the context
+// is GL 3.3 core, otherwise a framebuffer blit that only knows nearest and bilinear.
+pub struct Presenter {
+    filter: Filter,
+    backend: Backend,
+    // why the shader was not used, None without GL 3.3 core
+    error: Option<String>,
+}
+
+enum Backend {
+    Shader {
+        program: GLuint,
+        vertex_array: GLuint,
+        graphics_size: GLint,
+    },
+    Blit {
+        framebuffer: GLuint,
+    },
+}
+
+fn compile(kind: GLuint, source: &str) -> Result<GLuint, String> {
+    let source = CString::new(source).expect("shader source with a nul byte");
+    unsafe {
+        let shader = gl::CreateShader(kind);
+        gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+        let mut status = 0;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+        if status == gl::TRUE as GLint {
+            return Ok(shader);
+        }
+        let mut length = 0;
+        gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut length);
+        let mut log = vec![0u8; length.max(1) as usize];
+        gl::GetShaderInfoLog(
+            shader,
+            length,
+            ptr::null_mut(),
+            log.as_mut_ptr() as *mut GLchar,
+        );
+        gl::DeleteShader(shader);
+        Err(String::from_utf8_lossy(&log).to_string())
+    }
+}
+
+fn link(vertex: GLuint, fragment: GLuint) -> Result<GLuint, String> {
+    unsafe {
+        let program = gl::CreateProgram();
+        gl::AttachShader(program, vertex);
+        gl::AttachShader(program, fragment);
+        gl::LinkProgram(program);
+        gl::DeleteShader(vertex);
+        gl::DeleteShader(fragment);
+        let mut status = 0;
+        gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+        if status == gl::TRUE as GLint {
+            return Ok(program);
+        }
+        let mut length = 0;
+        gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut length);
+        let mut log = vec![0u8; length.max(1) as usize];
+        gl::GetProgramInfoLog(
+            program,
+            length,
+            ptr::null_mut(),
+            log.as_mut_ptr() as *mut GLchar,
+        );
+        gl::DeleteProgram(program);
+        Err(String::from_utf8_lossy(&log).to_string())
+    }
+}
+
+fn program() -> Result<GLuint, String> {
+    let vertex = compile(gl::VERTEX_SHADER, VERTEX_SHADER)?;
+    let fragment = match compile(gl::FRAGMENT_SHADER, FRAGMENT_SHADER) {
+        Ok(fragment) => fragment,
+        Err(error) => {
+            unsafe { gl::DeleteShader(vertex) };
+            return Err(error);
+        }
+    };
+    link(vertex, fragment)
+}
+
+// How the texture is sampled; the CRT effect starts from a smooth picture.
+fn sampling(filter: Filter) -> GLenum {
+    match filter {
+        Filter::Nearest => gl::NEAREST,
+        Filter::Bilinear | Filter::Crt => gl::LINEAR,
+    }
+}
+
+fn uniform(program: GLuint, name: &str) -> GLint {
+    let name = CString::new(name).expect("uniform name with a nul byte");
+    unsafe { gl::GetUniformLocation(program, name.as_ptr()) }
+}
+
+impl Presenter {
+    // `texture` has to be allocated already, the blit attaches it to a framebuffer.
+    pub fn new(texture: GLuint, filter: Filter, core: bool) -> Presenter {
+        let sampling = sampling(filter);
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, sampling as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, sampling as GLint);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            panic_gl("gl::TexParameteri");
+        }
+        let mut backend = None;
+        let mut error = None;
+        if core {
+            match program() {
+                Ok(program) => backend = Some(Presenter::shader(program, filter)),
+                Err(log) => error = Some(log),
+            }
+        }
+        let backend = match backend {
+            Some(backend) => backend,
+            None => Presenter::blit(texture),
+        };
+        Presenter {
+            filter,
+            backend,
+            error,
+        }
+    }
+    pub fn is_shader(&self) -> bool {
+        matches!(self.backend, Backend::Shader { .. })
+    }
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+    fn shader(program: GLuint, filter: Filter) -> Backend {
+        let mut vertex_array = 0;
+        let mut buffer = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vertex_array);
+            gl::BindVertexArray(vertex_array);
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffer);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&CORNERS) as isize,
+                CORNERS.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, ptr::null());
+            gl::EnableVertexAttribArray(0);
+            panic_gl("vertex array");
+            gl::UseProgram(program);
+            gl::Uniform1i(uniform(program, "graphics"), 0);
+            gl::Uniform1i(uniform(program, "crt"), (filter == Filter::Crt) as GLint);
+            panic_gl("gl::UseProgram");
+        }
+        Backend::Shader {
+            program,
+            vertex_array,
+            graphics_size: uniform(program, "graphics_size"),
+        }
+    }
+    fn blit(texture: GLuint) -> Backend {
+        let mut framebuffer = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut framebuffer);
+            panic_gl("gl::GenFramebuffers");
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, framebuffer);
+            panic_gl("gl::BindFramebuffer");
+            gl::FramebufferTexture2D(
+                gl::READ_FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture,
+                0,
+            );
+            panic_gl("gl::FramebufferTexture2D");
+        }
+        Backend::Blit { framebuffer }
+    }
+    // Draws the texture, `width` x `height`, into `rectangle` of the window.
+    pub fn present(&self, width: i32, height: i32, rectangle: Rectangle<i32>) {
+        let left = rectangle.position.x;
+        let bottom = rectangle.position.y;
+        match &self.backend {
+            Backend::Shader {
+                program,
+                vertex_array,
+                graphics_size,
+            } => unsafe {
+                gl::Viewport(left, bottom, rectangle.size.x, rectangle.size.y);
+                gl::UseProgram(*program);
+                gl::Uniform2f(*graphics_size, width as f32, height as f32);
+                gl::BindVertexArray(*vertex_array);
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+                panic_gl("gl::DrawArrays");
+            },
+            Backend::Blit { framebuffer } => unsafe {
+                let sampling = sampling(self.filter);
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER, *framebuffer);
+                gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+                gl::BlitFramebuffer(
+                    0,
+                    0,
+                    width,
+                    height,
+                    left,
+                    bottom,
+                    left + rectangle.size.x,
+                    bottom + rectangle.size.y,
+                    gl::COLOR_BUFFER_BIT,
+                    sampling,
+                );
+                panic_gl("gl::BlitFramebuffer");
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_nearest_keeps_pixels_sharp() {
+        assert_eq!(sampling(Filter::Nearest), gl::NEAREST);
+        assert_eq!(sampling(Filter::Bilinear), gl::LINEAR);
+        assert_eq!(sampling(Filter::Crt), gl::LINEAR);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::image::{font::Font, BlendMode, Color, Graphics};
+use crate::Vector2;
+use std::time::Duration;
+
+// Measurements of the last frame, and the frames and updates per second over the last second.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Stats {
+    // the last `Update` handler
+    pub update: Duration,
+    // the `Draw` handler, without the conversion
+    pub draw: Duration,
+    // `Sprite::update` of the graphics while drawing
+    pub convert: Duration,
+    // copying the changed pixels to the texture
+    pub upload: Duration,
+    // presenting the texture and swapping the buffers, waiting for vsync if enabled
+    pub swap: Duration,
+    pub fps: f64,
+    pub ups: f64,
+}
+
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.2}", duration.as_secs_f64() * 1000.)
+}
+
+impl Stats {
+    // Short lines, small graphics have little room.
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("fps {:.0}", self.fps),
+            format!("ups {:.0}", self.ups),
+            format!("upd {}", milliseconds(self.update)),
+            format!("drw {}", milliseconds(self.draw)),
+            format!("cnv {}", milliseconds(self.convert)),
+            format!("upl {}", milliseconds(self.upload)),
+            format!("swp {}", milliseconds(self.swap)),
+        ]
+    }
+    // The box `draw` fills, written with `font`.
+    pub fn size(&self, font: &Font) -> Vector2<u32> {
+        let lines = self.lines();
+        let width = lines
+            .iter()
+            .map(|line| font.text_width(line))
+            .max()
+            .unwrap_or(0);
+        Vector2::new(width + 2, font.line_height() * lines.len() as u32 + 1)
+    }
+    // On a dark box in the top left corner, with the graphics font.
+    pub fn draw(&self, graphics: &mut Graphics) {
+        let lines = self.lines();
+        let size = self.size(graphics.font());
+        let line_height = graphics.font().line_height();
+        let color = graphics.color();
+        let blend_mode = graphics.blend_mode();
+        graphics.set_blend_mode(BlendMode::Normal);
+        graphics.set_color(Color::from_rgba(0, 0, 0, 192));
+        graphics.fill_rectangle(0, 0, size.x, size.y);
+        graphics.set_color(Color::from_rgb(255, 255, 255));
+        for (index, line) in lines.iter().enumerate() {
+            graphics.draw_text(1, 1 + (line_height * index as u32) as i32, line);
+        }
+        graphics.set_color(color);
+        graphics.set_blend_mode(blend_mode);
+    }
+}
+
+// Keeps the stats while the window runs.
+#[derive(Default)]
+pub(crate) struct Meter {
+    stats: Stats,
+    frames: u32,
+    updates: u32,
+    elapsed: Duration,
+}
+
+impl Meter {
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+    pub fn update(&mut self, time: Duration) {
+        self.stats.update = time;
+        self.updates += 1;
+    }
+    pub fn draw(&mut self, time: Duration, convert: Duration) {
+        self.stats.draw = time.saturating_sub(convert);
+        self.stats.convert = convert;
+        self.frames += 1;
+    }
+    pub fn upload(&mut self, time: Duration) {
+        self.stats.upload = time;
+    }
+    pub fn swap(&mut self, time: Duration) {
+        self.stats.swap = time;
+    }
+    // The rates are recomputed once a second has passed.
+    pub fn advance(&mut self, delta: Duration) {
+        self.elapsed += delta;
+        if self.elapsed < Duration::from_secs(1) {
+            return;
+        }
+        let seconds = self.elapsed.as_secs_f64();
+        self.stats.fps = self.frames as f64 / seconds;
+        self.stats.ups = self.updates as f64 / seconds;
+        self.frames = 0;
+        self.updates = 0;
+        self.elapsed = Duration::ZERO;
+    }
+}
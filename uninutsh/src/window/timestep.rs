@@ -0,0 +1,134 @@
+use super::Redraw;
+use std::time::Duration;
+
+// Hands out fixed updates for the time that passed, see `WindowOptions::update_delta`.
+pub(crate) struct Timestep {
+    delta: Duration,
+    max_updates: u32,
+    // time not yet consumed by updates
+    accumulator: Duration,
+    // updates handed out since the last `advance`
+    updates: u32,
+}
+
+impl Timestep {
+    pub fn new(delta: Duration, max_updates: u32) -> Timestep {
+        Timestep {
+            delta,
+            max_updates: max_updates.max(1),
+            accumulator: Duration::ZERO,
+            updates: 0,
+        }
+    }
+    pub fn advance(&mut self, elapsed: Duration) {
+        self.accumulator += elapsed;
+        self.updates = 0;
+    }
+    // Whether one more update is due. Past `max_updates` the whole deltas left
+    // are dropped, so a stall does not turn into a burst of updates.
+    pub fn next_update(&mut self) -> bool {
+        if self.accumulator < self.delta {
+            return false;
+        }
+        if self.updates == self.max_updates {
+            self.accumulator = remainder(self.accumulator, self.delta);
+            return false;
+        }
+        self.accumulator -= self.delta;
+        self.updates += 1;
+        true
+    }
+    pub fn updates(&self) -> u32 {
+        self.updates
+    }
+    pub fn accumulated(&self) -> Duration {
+        self.accumulator
+    }
+    pub fn until_next_update(&self) -> Duration {
+        self.delta.saturating_sub(self.accumulator)
+    }
+}
+
+// What is left of `time` after taking out as many whole `delta` as it holds.
+fn remainder(time: Duration, delta: Duration) -> Duration {
+    if delta.is_zero() {
+        return Duration::ZERO;
+    }
+    Duration::from_nanos((time.as_nanos() % delta.as_nanos()) as u64)
+}
+
+impl Redraw {
+    // Whether a frame is due after `updates` updates, without `Window::redraw`.
+    pub(crate) fn is_due(self, updates: u32) -> bool {
+        match self {
+            Redraw::OnDemand => false,
+            Redraw::EveryUpdate => updates > 0,
+            Redraw::Vsync => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn milliseconds(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[test]
+    fn remainders() {
+        assert_eq!(
+            remainder(milliseconds(25), milliseconds(10)),
+            milliseconds(5)
+        );
+        assert_eq!(
+            remainder(milliseconds(30), milliseconds(10)),
+            Duration::ZERO
+        );
+        assert_eq!(
+            remainder(milliseconds(7), milliseconds(10)),
+            milliseconds(7)
+        );
+        assert_eq!(remainder(milliseconds(7), Duration::ZERO), Duration::ZERO);
+    }
+
+    fn run(timestep: &mut Timestep, elapsed: Duration) -> u32 {
+        timestep.advance(elapsed);
+        while timestep.next_update() {}
+        timestep.updates()
+    }
+
+    #[test]
+    fn updates_pay_for_the_elapsed_time() {
+        let mut timestep = Timestep::new(milliseconds(10), 3);
+        assert_eq!(run(&mut timestep, milliseconds(25)), 2);
+        assert_eq!(timestep.accumulated(), milliseconds(5));
+        assert_eq!(timestep.until_next_update(), milliseconds(5));
+        assert_eq!(run(&mut timestep, milliseconds(4)), 0);
+        assert_eq!(run(&mut timestep, milliseconds(1)), 1);
+        assert_eq!(timestep.accumulated(), Duration::ZERO);
+    }
+
+    #[test]
+    fn stalls_drop_the_updates_past_the_limit() {
+        let mut timestep = Timestep::new(milliseconds(10), 3);
+        assert_eq!(run(&mut timestep, milliseconds(107)), 3);
+        assert_eq!(timestep.accumulated(), milliseconds(7));
+        assert_eq!(run(&mut timestep, milliseconds(3)), 1);
+        // at least one update a frame
+        let mut timestep = Timestep::new(milliseconds(10), 0);
+        assert_eq!(run(&mut timestep, milliseconds(20)), 1);
+        assert_eq!(timestep.accumulated(), Duration::ZERO);
+    }
+
+    #[test]
+    fn redraw_policies() {
+        assert!(!Redraw::OnDemand.is_due(0));
+        assert!(!Redraw::OnDemand.is_due(2));
+        assert!(!Redraw::EveryUpdate.is_due(0));
+        assert!(Redraw::EveryUpdate.is_due(1));
+        assert!(Redraw::Vsync.is_due(0));
+        assert!(Redraw::Vsync.is_due(2));
+    }
+}